@@ -0,0 +1,314 @@
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::process::Command;
+use url::Url;
+
+// URL匹配条件：所有已设置的条件都满足才算匹配，未设置任何条件时匹配所有链接
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct UrlMatch {
+    // 域名，同时匹配其子域名
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hosts: Vec<String>,
+    // 路径扩展名，例如 "mp4"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extensions: Vec<String>,
+    // 对完整URL匹配的正则表达式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<Pattern>,
+}
+
+// 配置中的正则表达式：加载配置时编译一次，无效的表达式保留原文和错误信息，不匹配任何链接
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    source: String,
+    regex: Result<Regex, String>,
+}
+
+impl Pattern {
+    pub fn new(source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            regex: Regex::new(source).map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn regex(&self) -> Option<&Regex> {
+        self.regex.as_ref().ok()
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex().is_some_and(|regex| regex.is_match(text))
+    }
+
+    // 无法编译时的错误说明，用于在配置错误中提示
+    pub fn error(&self) -> Option<String> {
+        self.regex
+            .as_ref()
+            .err()
+            .map(|e| format!("正则表达式 {} 无效: {}", self.source, e))
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(|source| Pattern::new(&source))
+    }
+}
+
+impl UrlMatch {
+    pub fn is_empty(&self) -> bool {
+        self.hosts.is_empty() && self.extensions.is_empty() && self.pattern.is_none()
+    }
+
+    pub fn matches(&self, url: &str) -> bool {
+        let parsed = Url::parse(url).ok();
+
+        if !self.hosts.is_empty() {
+            let host = parsed.as_ref().and_then(|u| u.host_str()).unwrap_or("");
+            if !self.hosts.iter().any(|h| host_matches(host, h)) {
+                return false;
+            }
+        }
+
+        if !self.extensions.is_empty() {
+            let path = parsed.as_ref().map(|u| u.path()).unwrap_or("");
+            if !self.extensions.iter().any(|ext| has_extension(path, ext)) {
+                return false;
+            }
+        }
+
+        if let Some(pattern) = &self.pattern {
+            if !pattern.is_match(url) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+// 域名相等或为其子域名；写成 "*.example.com" 时只匹配子域名
+pub fn host_matches(host: &str, domain: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+    if let Some(parent) = domain.strip_prefix("*.") {
+        return host.ends_with(&format!(".{}", parent.to_ascii_lowercase()));
+    }
+    let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+// 路径最后一段是否以指定扩展名结尾（不区分大小写）
+pub fn has_extension(path: &str, ext: &str) -> bool {
+    let file_name = path.rsplit('/').next().unwrap_or("").to_ascii_lowercase();
+    let ext = ext.trim_start_matches('.').to_ascii_lowercase();
+    !ext.is_empty() && file_name.ends_with(&format!(".{}", ext))
+}
+
+// 非浏览器动作：把链接交给下载器、播放器或脚本
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Action {
+    pub name: String,
+    // 命令模板，支持 {url}、{host}、{path} 占位符；不含 {url} 时链接追加为最后一个参数
+    pub command: String,
+    #[serde(default, rename = "match", skip_serializing_if = "UrlMatch::is_empty")]
+    pub matches: UrlMatch,
+}
+
+// 适用于当前链接的动作
+pub fn matching_actions<'a>(actions: &'a [Action], url: &str) -> Vec<&'a Action> {
+    actions
+        .iter()
+        .filter(|a| !a.name.is_empty() && !a.command.trim().is_empty() && a.matches.matches(url))
+        .collect()
+}
+
+// 路由规则的目标
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RouteTarget {
+    Browser(String),
+    Action(String),
+    Group(String),
+}
+
+// 路由规则：匹配的链接直接交给目标处理，不再弹出选择窗口
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RoutingRule {
+    #[serde(rename = "match")]
+    pub matches: UrlMatch,
+    pub target: RouteTarget,
+}
+
+// 第一条匹配当前链接的规则（没有任何条件的规则不参与路由，避免吞掉所有链接）
+pub fn find_route<'a>(rules: &'a [RoutingRule], url: &str) -> Option<&'a RouteTarget> {
+    rules
+        .iter()
+        .find(|r| !r.matches.is_empty() && r.matches.matches(url))
+        .map(|r| &r.target)
+}
+
+// 按空白拆分命令行，双引号内的空白不拆分
+pub fn split_command(command: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_token = false;
+
+    for c in command.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_token = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_token {
+                    args.push(std::mem::take(&mut current));
+                    has_token = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_token = true;
+            }
+        }
+    }
+    if has_token {
+        args.push(current);
+    }
+    args
+}
+
+// 展开命令模板为参数列表
+pub fn expand_template(template: &str, url: &str) -> Vec<String> {
+    let parsed = Url::parse(url).ok();
+    let host = parsed.as_ref().and_then(|u| u.host_str()).unwrap_or("").to_string();
+    let path = parsed.as_ref().map(|u| u.path().to_string()).unwrap_or_default();
+
+    let placeholders = [("{url}", url), ("{host}", host.as_str()), ("{path}", path.as_str())];
+    let mut args: Vec<String> = split_command(template)
+        .into_iter()
+        .map(|arg| substitute(&arg, &placeholders))
+        .collect();
+    if !template.contains("{url}") {
+        args.push(url.to_string());
+    }
+    args
+}
+
+// 一次替换所有占位符，替换进来的内容（例如链接中的 "{path}"）不会再被替换
+fn substitute(arg: &str, placeholders: &[(&str, &str)]) -> String {
+    let mut result = String::new();
+    let mut rest = arg;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        match placeholders.iter().find(|(name, _)| tail.starts_with(name)) {
+            Some((name, value)) => {
+                result.push_str(value);
+                rest = &tail[name.len()..];
+            }
+            None => {
+                result.push('{');
+                rest = &tail[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+// 以第一个参数为程序启动进程
+pub fn spawn_args(args: &[String]) -> std::io::Result<()> {
+    let (program, rest) = args.split_first().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "命令为空")
+    })?;
+    Command::new(program).args(rest).spawn()?;
+    Ok(())
+}
+
+pub fn run_action(action: &Action, url: &str) -> std::io::Result<()> {
+    spawn_args(&expand_template(&action.command, url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url_match(json: &str) -> UrlMatch {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn matches_hosts_extensions_and_patterns() {
+        assert!(host_matches("www.bilibili.com", "bilibili.com"));
+        assert!(host_matches("Bilibili.com", ".bilibili.com"));
+        assert!(!host_matches("notbilibili.com", "bilibili.com"));
+
+        assert!(has_extension("/video/Movie.MP4", "mp4"));
+        assert!(has_extension("/a.tar.gz", ".gz"));
+        assert!(!has_extension("/mp4/index.html", "mp4"));
+        assert!(!has_extension("/file.mp4", ""));
+
+        let all = url_match(r#"{ "hosts": ["example.com"], "extensions": ["m3u8"], "pattern": "live" }"#);
+        assert!(all.matches("https://cdn.example.com/live/index.m3u8"));
+        assert!(!all.matches("https://cdn.example.com/vod/index.m3u8"));
+        assert!(!all.matches("https://cdn.example.com/live/index.mp4"));
+        assert!(!all.matches("https://other.com/live/index.m3u8"));
+
+        // 没有条件时匹配所有链接
+        assert!(UrlMatch::default().is_empty());
+        assert!(UrlMatch::default().matches("https://example.com/"));
+    }
+
+    #[test]
+    fn finds_first_matching_route() {
+        let rules: Vec<RoutingRule> = serde_json::from_str(
+            r#"[
+                { "match": {}, "target": { "browser": "everything" } },
+                { "match": { "hosts": ["github.com"] }, "target": { "browser": "firefox" } },
+                { "match": { "extensions": ["mp4"] }, "target": { "action": "播放" } },
+                { "match": { "hosts": ["github.com"] }, "target": { "group": "工作" } }
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(find_route(&rules, "https://gist.github.com/x"), Some(&RouteTarget::Browser("firefox".to_string())));
+        assert_eq!(find_route(&rules, "https://a.com/v.mp4"), Some(&RouteTarget::Action("播放".to_string())));
+        // 没有条件的规则不参与路由
+        assert_eq!(find_route(&rules, "https://a.com/"), None);
+    }
+
+    #[test]
+    fn splits_commands_with_quotes() {
+        assert_eq!(split_command("  mpv   --fs  "), vec!["mpv", "--fs"]);
+        assert_eq!(
+            split_command(r#""C:\Program Files\mpv\mpv.exe" --title="a b" {url}"#),
+            vec![r"C:\Program Files\mpv\mpv.exe", "--title=a b", "{url}"]
+        );
+        // 反斜杠不是转义符，Windows 路径原样保留；空引号是一个空参数
+        assert_eq!(split_command(r#"cmd \"x\" """#), vec!["cmd", r"\x\", ""]);
+    }
+
+    #[test]
+    fn expands_templates() {
+        let url = "https://example.com/watch/{path}?v=1";
+        assert_eq!(
+            expand_template("yt-dlp -o {host}{path} {url}", url),
+            vec!["yt-dlp", "-o", "example.com/watch/%7Bpath%7D", url]
+        );
+        // 未写 {url} 时链接追加到末尾
+        assert_eq!(expand_template("wget -q", "https://a.com/x"), vec!["wget", "-q", "https://a.com/x"]);
+        assert_eq!(expand_template("echo {unknown}", "https://a.com/"), vec!["echo", "{unknown}", "https://a.com/"]);
+    }
+}
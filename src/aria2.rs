@@ -0,0 +1,258 @@
+use crate::actions::has_extension;
+use crate::probe::{self, HeadInfo};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+use url::Url;
+
+// 常见的直接下载文件扩展名
+const DOWNLOAD_EXTENSIONS: &[&str] = &[
+    "zip", "rar", "7z", "tar", "gz", "tgz", "bz2", "xz", "zst", "exe", "msi", "msix", "dmg", "pkg",
+    "deb", "rpm", "appimage", "apk", "iso", "img", "bin", "torrent",
+];
+
+// 表示文件下载而非网页的Content-Type
+const DOWNLOAD_CONTENT_TYPES: &[&str] = &[
+    "application/octet-stream",
+    "application/zip",
+    "application/x-zip-compressed",
+    "application/x-7z-compressed",
+    "application/vnd.rar",
+    "application/x-rar-compressed",
+    "application/gzip",
+    "application/x-tar",
+    "application/x-msdownload",
+    "application/x-msi",
+    "application/x-iso9660-image",
+    "application/vnd.android.package-archive",
+    "application/x-bittorrent",
+];
+
+fn default_endpoint() -> String {
+    "http://localhost:6800/jsonrpc".to_string()
+}
+
+// aria2 JSON-RPC 设置，配置文件中存在该项时才在选择窗口中提供“发送到 aria2”
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Aria2Config {
+    #[serde(default = "default_endpoint")]
+    pub endpoint: String,
+    // 对应 aria2 的 --rpc-secret
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    // 下载目录，未设置时使用 aria2 自身的默认目录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dir: Option<String>,
+    // 扩展名无法判断时是否发送HEAD请求检查响应头
+    #[serde(default = "probe::enabled_by_default")]
+    pub probe: bool,
+}
+
+impl Default for Aria2Config {
+    fn default() -> Self {
+        Self {
+            endpoint: default_endpoint(),
+            secret: None,
+            dir: None,
+            probe: probe::enabled_by_default(),
+        }
+    }
+}
+
+// 仅凭扩展名判断链接是否指向文件
+pub fn has_download_extension(url: &str) -> bool {
+    Url::parse(url)
+        .map(|u| DOWNLOAD_EXTENSIONS.iter().any(|ext| has_extension(u.path(), ext)))
+        .unwrap_or(false)
+}
+
+// 结合HEAD响应头判断链接是否指向文件
+pub fn is_download(url: &str, head: Option<&HeadInfo>) -> bool {
+    if has_download_extension(url) {
+        return true;
+    }
+    match head {
+        Some(info) => {
+            info.is_attachment()
+                || info
+                    .mime_type()
+                    .map(|mime| DOWNLOAD_CONTENT_TYPES.contains(&mime.as_str()))
+                    .unwrap_or(false)
+        }
+        None => false,
+    }
+}
+
+// 调用 aria2.addUri 添加下载任务，成功时返回任务的GID
+pub fn add_uri(config: &Aria2Config, url: &str) -> Result<String, String> {
+    let mut params = Vec::new();
+    if let Some(secret) = config.secret.as_deref().filter(|s| !s.is_empty()) {
+        params.push(json!(format!("token:{}", secret)));
+    }
+    params.push(json!([url]));
+    if let Some(dir) = config.dir.as_deref().filter(|d| !d.is_empty()) {
+        params.push(json!({ "dir": dir }));
+    }
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": "fuckHttp",
+        "method": "aria2.addUri",
+        "params": params,
+    });
+
+    let client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| format!("无法创建HTTP客户端: {}", e))?;
+    let body = client
+        .post(&config.endpoint)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(request.to_string())
+        .send()
+        .and_then(|response| response.text())
+        .map_err(|e| format!("无法连接 aria2: {}", e))?;
+
+    let response: Value =
+        serde_json::from_str(&body).map_err(|_| "aria2 返回了无效的响应".to_string())?;
+    if let Some(gid) = response.get("result").and_then(Value::as_str) {
+        return Ok(gid.to_string());
+    }
+    let message = response
+        .pointer("/error/message")
+        .and_then(Value::as_str)
+        .unwrap_or("未知错误");
+    Err(format!("aria2 错误: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    // 只处理一个请求的 JSON-RPC 模拟服务器，返回服务器地址和收到的请求体
+    fn mock_rpc_server(response_body: &'static str) -> (String, JoinHandle<Value>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/jsonrpc", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            let mut stream = stream;
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                response_body.len(),
+                response_body
+            )
+            .unwrap();
+            serde_json::from_slice(&body).unwrap()
+        });
+        (endpoint, handle)
+    }
+
+    #[test]
+    fn add_uri_sends_token_and_dir() {
+        let (endpoint, server) =
+            mock_rpc_server(r#"{"jsonrpc":"2.0","id":"fuckHttp","result":"2089b05ecca3d829"}"#);
+        let config = Aria2Config {
+            endpoint,
+            secret: Some("s3cret".to_string()),
+            dir: Some("/downloads".to_string()),
+            probe: true,
+        };
+
+        let gid = add_uri(&config, "https://example.com/file.zip").unwrap();
+        assert_eq!(gid, "2089b05ecca3d829");
+
+        let request = server.join().unwrap();
+        assert_eq!(request["method"], "aria2.addUri");
+        assert_eq!(
+            request["params"],
+            json!(["token:s3cret", ["https://example.com/file.zip"], { "dir": "/downloads" }])
+        );
+    }
+
+    #[test]
+    fn add_uri_without_secret_or_dir() {
+        let (endpoint, server) = mock_rpc_server(r#"{"jsonrpc":"2.0","id":"fuckHttp","result":"1"}"#);
+        let config = Aria2Config {
+            endpoint,
+            ..Default::default()
+        };
+
+        add_uri(&config, "https://example.com/a.iso").unwrap();
+        let request = server.join().unwrap();
+        assert_eq!(request["params"], json!([["https://example.com/a.iso"]]));
+    }
+
+    #[test]
+    fn add_uri_reports_rpc_error() {
+        let (endpoint, server) = mock_rpc_server(
+            r#"{"jsonrpc":"2.0","id":"fuckHttp","error":{"code":1,"message":"Unauthorized"}}"#,
+        );
+        let config = Aria2Config {
+            endpoint,
+            ..Default::default()
+        };
+
+        let err = add_uri(&config, "https://example.com/file.zip").unwrap_err();
+        assert_eq!(err, "aria2 错误: Unauthorized");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn add_uri_reports_connection_failure() {
+        // 绑定后立即释放端口，保证没有服务在监听
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let config = Aria2Config {
+            endpoint: format!("http://127.0.0.1:{}/jsonrpc", port),
+            ..Default::default()
+        };
+
+        let err = add_uri(&config, "https://example.com/file.zip").unwrap_err();
+        assert!(err.starts_with("无法连接 aria2"), "{}", err);
+    }
+
+    #[test]
+    fn detects_downloads_by_extension_and_headers() {
+        assert!(is_download("https://example.com/setup.EXE", None));
+        assert!(is_download("https://example.com/a/b.tar.gz?x=1", None));
+        assert!(!is_download("https://example.com/index.html", None));
+
+        let attachment = HeadInfo {
+            content_type: Some("text/plain".to_string()),
+            content_disposition: Some("attachment; filename=\"notes.txt\"".to_string()),
+        };
+        assert!(is_download("https://example.com/download?id=1", Some(&attachment)));
+
+        let binary = HeadInfo {
+            content_type: Some("application/octet-stream; charset=binary".to_string()),
+            content_disposition: None,
+        };
+        assert!(is_download("https://example.com/download?id=1", Some(&binary)));
+
+        let page = HeadInfo {
+            content_type: Some("text/html; charset=utf-8".to_string()),
+            content_disposition: None,
+        };
+        assert!(!is_download("https://example.com/download?id=1", Some(&page)));
+    }
+}
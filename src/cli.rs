@@ -0,0 +1,666 @@
+use crate::{
+    apply_routing, config_value, extract_from_wechat_page, extract_with_extractor, find_browser, get_installed_browsers,
+    launch_browser, load_config, ordering, paths, platform, routing_url, run_selector, write_config, Browser, Config,
+    Extractor,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt::Display;
+use std::io::{self, BufRead};
+
+// 退出码，供脚本判断结果
+pub const EXIT_OK: i32 = 0;
+// 操作失败：启动浏览器、注册、保存配置等
+pub const EXIT_FAILURE: i32 = 1;
+// 命令行用法错误或配置值无效
+pub const EXIT_USAGE: i32 = 2;
+// 找不到指定的浏览器、配置方案或配置项
+pub const EXIT_NOT_FOUND: i32 = 3;
+// status：尚未注册为默认浏览器
+pub const EXIT_NOT_REGISTERED: i32 = 4;
+
+// 没有指定链接时在选择窗口中显示的链接
+const DEFAULT_URL: &str = "https://www.google.com";
+
+const HELP: &str = "fuckHttp - 为被拦截的链接选择浏览器
+
+用法:
+  fuckHttp [<链接>] [选项]         显示选择窗口，同 open
+  fuckHttp open [<链接>] [选项]    命中路由规则时直接打开，否则显示选择窗口
+      --browser <名称>             直接用指定的浏览器打开（浏览器ID、名称或别名）
+      --profile <方案>             本次使用指定的配置方案
+      --private                    以隐私模式打开
+  fuckHttp extract [<链接>...]     输出提取出的真实链接，不写链接时从标准输入每行读取一个
+      --json                       每行输出一个 JSON：original、result、extractor、error
+  fuckHttp browsers list           列出浏览器：ID、显示名称、状态，以制表符分隔
+  fuckHttp register                注册为默认浏览器
+  fuckHttp unregister              取消注册
+  fuckHttp status                  显示注册状态和配置信息
+  fuckHttp config get [<键>]       输出配置项，不写键时输出整个配置
+  fuckHttp config set <键> <值>    修改配置项；值按 JSON 解析，不是 JSON 时作为字符串，null 表示删除
+  fuckHttp config path             输出配置文件的路径
+  fuckHttp help                    显示本帮助
+
+配置项的键用 . 分隔层级，例如 aria2.endpoint；键本身含 . 时用 JSON 指针，例如 /aliases/firefox.desktop|firefox
+
+退出码:
+  0  成功
+  1  操作失败（extract：有链接提取失败）
+  2  用法错误或配置值无效
+  3  找不到指定的浏览器、配置方案或配置项
+  4  尚未注册为默认浏览器（status）
+";
+
+// open 子命令的参数
+#[derive(Debug, Default, PartialEq)]
+pub struct OpenArgs {
+    pub url: Option<String>,
+    pub browser: Option<String>,
+    pub profile: Option<String>,
+    pub private: bool,
+}
+
+// extract 子命令的参数；urls 为空时从标准输入读取
+#[derive(Debug, Default, PartialEq)]
+pub struct ExtractArgs {
+    pub urls: Vec<String>,
+    pub json: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Open(OpenArgs),
+    Extract(ExtractArgs),
+    ListBrowsers,
+    Register,
+    Unregister,
+    Status,
+    ConfigGet(Option<String>),
+    ConfigSet(String, String),
+    ConfigPath,
+    Help,
+}
+
+// 选项的值：--browser=Chrome 或 --browser Chrome
+fn option_value<'a>(
+    flag: &str,
+    inline: Option<&str>,
+    rest: &mut impl Iterator<Item = &'a String>,
+) -> Result<String, String> {
+    inline
+        .map(str::to_string)
+        .or_else(|| rest.next().cloned())
+        .ok_or_else(|| format!("{} 需要一个值", flag))
+}
+
+fn parse_open(args: &[String]) -> Result<OpenArgs, String> {
+    let mut open = OpenArgs::default();
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+            _ => (arg.as_str(), None),
+        };
+        match flag {
+            "--browser" => open.browser = Some(option_value(flag, inline, &mut rest)?),
+            "--profile" => open.profile = Some(option_value(flag, inline, &mut rest)?),
+            "--private" if inline.is_none() => open.private = true,
+            _ if arg.starts_with('-') => return Err(format!("未知的选项: {}", arg)),
+            _ if open.url.is_none() => open.url = Some(arg.clone()),
+            _ => return Err(format!("多余的参数: {}", arg)),
+        }
+    }
+    Ok(open)
+}
+
+fn parse_extract(args: &[String]) -> Result<ExtractArgs, String> {
+    let mut extract = ExtractArgs::default();
+    for arg in args {
+        match arg.as_str() {
+            "--json" => extract.json = true,
+            // 和其他命令行工具一样，- 表示标准输入
+            "-" => {}
+            _ if arg.starts_with('-') => return Err(format!("未知的选项: {}", arg)),
+            _ => extract.urls.push(arg.clone()),
+        }
+    }
+    Ok(extract)
+}
+
+// 解析命令行参数（不含程序名）；不是子命令时按 open 处理，兼容注册的协议命令 "fuckHttp.exe" "%1"
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    let Some((first, rest)) = args.split_first() else {
+        return Ok(Command::Open(OpenArgs::default()));
+    };
+    let words: Vec<&str> = rest.iter().map(String::as_str).collect();
+    let command = match (first.as_str(), words.as_slice()) {
+        ("help" | "--help" | "-h", _) => Command::Help,
+        ("open", _) => Command::Open(parse_open(rest)?),
+        ("extract", _) => Command::Extract(parse_extract(rest)?),
+        ("browsers", ["list"]) => Command::ListBrowsers,
+        ("browsers", _) => return Err("用法: fuckHttp browsers list".to_string()),
+        // 旧版本的 --register/--unregister 作为已弃用的别名保留
+        ("register" | "--register", []) => Command::Register,
+        ("unregister" | "--unregister", []) => Command::Unregister,
+        ("status", []) => Command::Status,
+        ("register" | "unregister" | "--register" | "--unregister" | "status", _) => {
+            return Err(format!("{} 不需要参数", first))
+        }
+        ("config", ["get"]) => Command::ConfigGet(None),
+        ("config", ["get", key]) => Command::ConfigGet(Some(key.to_string())),
+        ("config", ["set", key, value]) => Command::ConfigSet(key.to_string(), value.to_string()),
+        ("config", ["path"]) => Command::ConfigPath,
+        ("config", _) => return Err("用法: fuckHttp config get [<键>] | set <键> <值> | path".to_string()),
+        _ => Command::Open(parse_open(args)?),
+    };
+    Ok(command)
+}
+
+// 向终端输出提示；选择窗口模式下此时才连接控制台
+fn warn(message: impl Display) {
+    platform::current().attach_console();
+    eprintln!("{}", message);
+}
+
+// 输出错误并返回退出码
+fn fail(code: i32, message: impl Display) -> i32 {
+    warn(message);
+    code
+}
+
+// 执行命令行，返回退出码
+pub fn run(args: &[String]) -> i32 {
+    let command = match parse(args) {
+        Ok(command) => command,
+        Err(e) => return fail(EXIT_USAGE, format!("{}\n运行 fuckHttp help 查看用法", e)),
+    };
+    // 选择窗口不需要控制台，其余命令都向终端输出
+    if !matches!(command, Command::Open(_)) {
+        platform::current().attach_console();
+    }
+    if let Some(old @ ("--register" | "--unregister")) = args.first().map(String::as_str) {
+        warn(format!("{} 已弃用，请改用 fuckHttp {}", old, old.trim_start_matches('-')));
+    }
+    match command {
+        Command::Open(open_args) => open(open_args),
+        Command::Extract(extract_args) => extract(extract_args),
+        Command::ListBrowsers => list_browsers(),
+        Command::Register => register(false),
+        Command::Unregister => register(true),
+        Command::Status => status(),
+        Command::ConfigGet(key) => config_get(key.as_deref()),
+        Command::ConfigSet(key, value) => config_set(&key, &value),
+        Command::ConfigPath => {
+            println!("{}", paths::config_file().display());
+            EXIT_OK
+        }
+        Command::Help => {
+            print!("{}", HELP);
+            EXIT_OK
+        }
+    }
+}
+
+// 一个链接的提取结果，--json 时每行输出一个
+#[derive(Debug, PartialEq, Serialize)]
+struct Extraction {
+    original: String,
+    // 提取失败时为 None
+    result: Option<String>,
+    extractor: &'static str,
+    error: Option<String>,
+}
+
+// 完整的提取流程：先从链接参数中提取，微信拦截页面在允许联网时访问页面提取
+fn resolve_url(url: &str, network_allowed: bool) -> Extraction {
+    let (extracted, extractor) = extract_with_extractor(url);
+    let resolved = match extractor {
+        Extractor::WeChat if !network_allowed => Err("已禁用联网提取".to_string()),
+        Extractor::WeChat => extract_from_wechat_page(url).ok_or_else(|| "无法从微信页面提取链接".to_string()),
+        _ => Ok(extracted),
+    };
+    let (result, error) = match resolved {
+        Ok(result) => (Some(result), None),
+        Err(e) => (None, Some(e)),
+    };
+    Extraction {
+        original: url.to_string(),
+        result,
+        extractor: extractor.name(),
+        error,
+    }
+}
+
+fn open(args: OpenArgs) -> i32 {
+    let (mut config, config_error) = load_config();
+    if let Some(name) = args.profile {
+        if !config.profiles.contains_key(&name) {
+            return fail(EXIT_NOT_FOUND, format!("配置方案不存在: {}", name));
+        }
+        config.profile_override = Some(name);
+    }
+    let browsers = get_installed_browsers(&mut config);
+    let url = args.url.unwrap_or_else(|| DEFAULT_URL.to_string());
+
+    let Some(name) = args.browser else {
+        // 命中路由规则的链接直接打开，不显示选择窗口
+        if let Some(routed) = routing_url(&url) {
+            if apply_routing(&config, &browsers, &routed, args.private) {
+                return EXIT_OK;
+            }
+        }
+        return match run_selector(url, browsers, config, config_error, args.private) {
+            Ok(()) => EXIT_OK,
+            Err(e) => fail(EXIT_FAILURE, format!("无法显示选择窗口: {}", e)),
+        };
+    };
+
+    let allowed: Vec<&Browser> = browsers.iter().filter(|b| !config.policy.blocks(b)).collect();
+    let Some(browser) = find_browser(&config, &allowed, &name) else {
+        return fail(EXIT_NOT_FOUND, format!("找不到浏览器: {}（运行 fuckHttp browsers list 查看）", name));
+    };
+    // 提取失败时和选择窗口一样打开原链接
+    let extraction = resolve_url(&url, config.network_extraction_allowed());
+    let url = match extraction.result {
+        Some(resolved) => resolved,
+        None => {
+            warn(format!("提取失败，打开原链接: {}", extraction.error.unwrap_or_default()));
+            url
+        }
+    };
+    if let Err(e) = launch_browser(browser, &url, args.private) {
+        return fail(EXIT_FAILURE, format!("启动 {} 失败: {}", config.display_name(browser), e));
+    }
+    ordering::record(&mut config.usage, &browser.id, ordering::now());
+    // 浏览器已经打开，保存失败只提示，避免脚本重试时再打开一次
+    if let Err(e) = write_config(&config) {
+        warn(format!("保存使用记录失败: {}", e));
+    }
+    EXIT_OK
+}
+
+// 纯文本时每行输出一个结果，提取失败的输出原链接以保持行数一致，错误原因写到标准错误
+fn extraction_line(extraction: &Extraction, json: bool) -> String {
+    if json {
+        return serde_json::to_string(extraction).unwrap_or_default();
+    }
+    extraction.result.clone().unwrap_or_else(|| extraction.original.clone())
+}
+
+// 不显示窗口，逐个提取命令行或标准输入中的链接
+fn extract(args: ExtractArgs) -> i32 {
+    let (config, _) = load_config();
+    let network_allowed = config.network_extraction_allowed();
+    let urls: Box<dyn Iterator<Item = String>> = if args.urls.is_empty() {
+        Box::new(io::stdin().lock().lines().map_while(Result::ok))
+    } else {
+        Box::new(args.urls.into_iter())
+    };
+
+    let mut code = EXIT_OK;
+    for url in urls {
+        let url = url.trim();
+        if url.is_empty() {
+            continue;
+        }
+        let extraction = resolve_url(url, network_allowed);
+        if let Some(error) = &extraction.error {
+            if !args.json {
+                eprintln!("{}: {}", url, error);
+            }
+            code = EXIT_FAILURE;
+        }
+        println!("{}", extraction_line(&extraction, args.json));
+    }
+    code
+}
+
+// browsers list 的一行：ID、显示名称、状态
+fn browser_line(config: &Config, browser: &Browser) -> String {
+    let mut states = Vec::new();
+    if config.profile().default_browser.as_ref() == Some(&browser.id) {
+        states.push("默认".to_string());
+    }
+    if config.policy.blocks(browser) {
+        states.push("已被策略禁用".to_string());
+    } else if browser.hidden {
+        states.push("隐藏".to_string());
+    }
+    if let Some(reason) = &browser.broken {
+        states.push(format!("无法启动: {}", reason));
+    }
+    format!("{}\t{}\t{}", browser.id, config.display_name(browser), states.join(", "))
+}
+
+fn list_browsers() -> i32 {
+    let (mut config, _) = load_config();
+    for browser in get_installed_browsers(&mut config) {
+        println!("{}", browser_line(&config, &browser));
+    }
+    EXIT_OK
+}
+
+// 需要管理员权限时以管理员身份重新运行同一子命令
+fn register(unregister: bool) -> i32 {
+    let platform = platform::current();
+    let (command, done, action) = match unregister {
+        true => ("unregister", "已取消注册", "卸载"),
+        false => ("register", "已注册为默认浏览器", "注册"),
+    };
+    if !platform.is_elevated() {
+        if platform.run_elevated(command) {
+            println!("{}", done);
+            return EXIT_OK;
+        }
+        return fail(EXIT_FAILURE, format!("{}失败 (需要管理员权限)", action));
+    }
+    let result = match unregister {
+        true => platform.unregister(),
+        false => platform.register(),
+    };
+    match result {
+        Ok(()) => {
+            println!("{}", done);
+            EXIT_OK
+        }
+        Err(e) => fail(EXIT_FAILURE, format!("{}失败: {}", action, e)),
+    }
+}
+
+fn status() -> i32 {
+    let registered = platform::current().is_registered();
+    let (config, error) = load_config();
+    println!("默认浏览器: {}", if registered { "已注册" } else { "未注册" });
+    println!("配置文件: {}", paths::config_file().display());
+    if paths::portable_data_dir().is_some() {
+        println!("便携模式: 是");
+    }
+    println!("配置方案: {}", config.profile_name().unwrap_or("默认"));
+    println!("联网提取: {}", if config.network_extraction_allowed() { "允许" } else { "禁用" });
+    if let Some(preset) = &config.team_preset {
+        println!("团队预设: {}", preset.display());
+    }
+    if !config.policy.is_empty() {
+        println!("管理员策略: 已生效");
+    }
+    for error in [error, config.preset_error.clone(), config.policy_error.clone(), config.pattern_error()].into_iter().flatten() {
+        eprintln!("⚠ {}", error);
+    }
+    if registered {
+        EXIT_OK
+    } else {
+        EXIT_NOT_REGISTERED
+    }
+}
+
+// 配置项的键：JSON 指针（/aliases/x.y）或以 . 分隔的路径（aria2.endpoint）
+fn key_segments(key: &str) -> Vec<String> {
+    match key.strip_prefix('/') {
+        Some(pointer) => pointer.split('/').map(|s| s.replace("~1", "/").replace("~0", "~")).collect(),
+        None => key.split('.').map(str::to_string).collect(),
+    }
+}
+
+fn lookup<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    key_segments(key).iter().try_fold(value, |value, segment| match value {
+        Value::Object(map) => map.get(segment),
+        Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+// 设置或删除（None）配置项，缺少的中间层对象自动创建；数组与 lookup 一样按下标访问，下标等于长度时追加。
+// 路径经过其他值或下标超出范围时返回 false
+fn assign(value: &mut Value, key: &str, new_value: Option<Value>) -> bool {
+    let segments = key_segments(key);
+    let Some((last, parents)) = segments.split_last() else {
+        return false;
+    };
+    let mut current = value;
+    for segment in parents {
+        current = match current {
+            Value::Object(map) => map.entry(segment.clone()).or_insert_with(|| Value::Object(Default::default())),
+            Value::Array(items) => match segment.parse::<usize>().ok().and_then(|index| items.get_mut(index)) {
+                Some(item) => item,
+                None => return false,
+            },
+            _ => return false,
+        };
+    }
+    match current {
+        Value::Object(map) => {
+            match new_value {
+                Some(new_value) => map.insert(last.clone(), new_value),
+                None => map.remove(last),
+            };
+        }
+        Value::Array(items) => {
+            let Ok(index) = last.parse::<usize>() else {
+                return false;
+            };
+            match new_value {
+                Some(new_value) if index < items.len() => items[index] = new_value,
+                Some(new_value) if index == items.len() => items.push(new_value),
+                None if index < items.len() => {
+                    items.remove(index);
+                }
+                _ => return false,
+            }
+        }
+        _ => return false,
+    }
+    true
+}
+
+// 输出配置值：字符串原样输出，其他值输出 JSON
+fn print_value(value: &Value) {
+    match value {
+        Value::String(text) => println!("{}", text),
+        other => println!("{}", serde_json::to_string_pretty(other).unwrap_or_default()),
+    }
+}
+
+// 修改配置中的一项，值为 null 时删除（有默认值的项恢复默认值）；失败时返回退出码和原因
+fn set_config_value(config: &Config, key: &str, raw: &str) -> Result<Config, (i32, String)> {
+    let mut value = config_value(config);
+    let new_value = match serde_json::from_str(raw) {
+        Ok(Value::Null) => None,
+        Ok(parsed) => Some(parsed),
+        Err(_) => Some(Value::String(raw.to_string())),
+    };
+    if new_value.is_none() && lookup(&value, key).is_none() {
+        return Err((EXIT_NOT_FOUND, format!("配置项不存在或未设置: {}", key)));
+    }
+    if !assign(&mut value, key, new_value.clone()) {
+        return Err((EXIT_USAGE, format!("无法设置 {}：上一级不是对象或数组，或下标超出范围", key)));
+    }
+    let updated: Config =
+        serde_json::from_value(value).map_err(|e| (EXIT_USAGE, format!("{} 的值无效: {}", key, e)))?;
+    // 未知的配置项在解析时被丢弃，写回前确认确实生效
+    if new_value.is_some() && lookup(&config_value(&updated), key) != new_value.as_ref() {
+        return Err((EXIT_NOT_FOUND, format!("未知的配置项: {}", key)));
+    }
+    Ok(updated)
+}
+
+// 读取自己的配置文件中的值，不含团队预设提供的条目
+fn config_get(key: Option<&str>) -> i32 {
+    let (config, _) = load_config();
+    let value = config_value(&config);
+    let Some(key) = key else {
+        print_value(&value);
+        return EXIT_OK;
+    };
+    match lookup(&value, key) {
+        Some(found) => {
+            print_value(found);
+            EXIT_OK
+        }
+        None => fail(EXIT_NOT_FOUND, format!("配置项不存在或未设置: {}", key)),
+    }
+}
+
+fn config_set(key: &str, raw: &str) -> i32 {
+    let (config, _) = load_config();
+    let updated = match set_config_value(&config, key, raw) {
+        Ok(updated) => updated,
+        Err((code, message)) => return fail(code, message),
+    };
+    match write_config(&updated) {
+        Ok(()) => EXIT_OK,
+        Err(e) => fail(EXIT_FAILURE, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::RouteTarget;
+    use serde_json::json;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn parses_subcommands() {
+        assert_eq!(parse(&[]), Ok(Command::Open(OpenArgs::default())));
+        assert_eq!(parse(&args("--help")), Ok(Command::Help));
+        let extract = ExtractArgs {
+            urls: vec!["https://a.com".to_string(), "https://b.com".to_string()],
+            json: true,
+        };
+        assert_eq!(parse(&args("extract https://a.com --json https://b.com")), Ok(Command::Extract(extract)));
+        assert_eq!(parse(&args("extract -")), Ok(Command::Extract(ExtractArgs::default())));
+        assert_eq!(parse(&args("browsers list")), Ok(Command::ListBrowsers));
+        assert_eq!(parse(&args("status")), Ok(Command::Status));
+        assert_eq!(parse(&args("--register")), Ok(Command::Register));
+        assert_eq!(parse(&args("--unregister")), Ok(Command::Unregister));
+        assert_eq!(parse(&args("config get aria2.endpoint")), Ok(Command::ConfigGet(Some("aria2.endpoint".to_string()))));
+        assert_eq!(
+            parse(&args("config set icon_grid true")),
+            Ok(Command::ConfigSet("icon_grid".to_string(), "true".to_string()))
+        );
+        assert!(parse(&args("extract --plain")).is_err());
+        assert!(parse(&args("browsers")).is_err());
+        assert!(parse(&args("register now")).is_err());
+        assert!(parse(&args("config set icon_grid")).is_err());
+    }
+
+    #[test]
+    fn parses_open_options() {
+        let expected = OpenArgs {
+            url: Some("https://a.com/?x=1".to_string()),
+            browser: Some("Firefox".to_string()),
+            profile: Some("工作".to_string()),
+            private: true,
+        };
+        let line = "open https://a.com/?x=1 --browser Firefox --profile=工作 --private";
+        assert_eq!(parse(&args(line)), Ok(Command::Open(expected)));
+
+        // 注册的协议命令只传入链接
+        let url_only = OpenArgs {
+            url: Some("https://a.com".to_string()),
+            ..OpenArgs::default()
+        };
+        assert_eq!(parse(&args("https://a.com")), Ok(Command::Open(url_only)));
+
+        assert!(parse(&args("open --browser")).is_err());
+        assert!(parse(&args("open --incognito https://a.com")).is_err());
+        assert!(parse(&args("open https://a.com https://b.com")).is_err());
+    }
+
+    #[test]
+    fn extracts_without_network_when_disallowed() {
+        let qq = resolve_url("https://c.pc.qq.com/ios.html?level=14&url=https%3A%2F%2Fexample.com%2Fa", false);
+        assert_eq!(qq.result.as_deref(), Some("https://example.com/a"));
+        assert_eq!(qq.extractor, "qq");
+        assert_eq!(extraction_line(&qq, false), "https://example.com/a");
+
+        let plain = resolve_url("https://example.com/", false);
+        assert_eq!((plain.result.as_deref(), plain.extractor), (Some("https://example.com/"), "none"));
+
+        // 禁用联网提取时不访问微信页面
+        let wechat_url = "https://weixin110.qq.com/security/readtemplate?t=x";
+        let wechat = resolve_url(wechat_url, false);
+        assert_eq!(wechat.result, None);
+        assert_eq!(wechat.extractor, "wechat");
+        assert_eq!(extraction_line(&wechat, false), wechat_url);
+        assert_eq!(
+            serde_json::from_str::<Value>(&extraction_line(&wechat, true)).unwrap(),
+            json!({ "original": wechat_url, "result": null, "extractor": "wechat", "error": "已禁用联网提取" })
+        );
+    }
+
+    #[test]
+    fn reads_and_writes_config_keys() {
+        let mut value = json!({ "icon_grid": false, "aliases": { "firefox.desktop|firefox": "Firefox" }, "rules": [{ "x": 1 }] });
+        assert_eq!(lookup(&value, "icon_grid"), Some(&json!(false)));
+        assert_eq!(lookup(&value, "/aliases/firefox.desktop|firefox"), Some(&json!("Firefox")));
+        assert_eq!(lookup(&value, "rules.0.x"), Some(&json!(1)));
+        assert_eq!(lookup(&value, "aria2.endpoint"), None);
+
+        assert!(assign(&mut value, "aria2.endpoint", Some(json!("http://nas:6800/jsonrpc"))));
+        assert_eq!(value["aria2"], json!({ "endpoint": "http://nas:6800/jsonrpc" }));
+        assert!(assign(&mut value, "/aliases/firefox.desktop|firefox", None));
+        assert_eq!(value["aliases"], json!({}));
+        assert!(!assign(&mut value, "icon_grid.size", Some(json!(1))));
+
+        // 数组按下标读写，与 lookup 一致
+        assert!(assign(&mut value, "rules.0.x", Some(json!(2))));
+        assert_eq!(lookup(&value, "rules.0.x"), Some(&json!(2)));
+        assert!(assign(&mut value, "rules.0", Some(json!({ "y": 1 }))));
+        assert!(assign(&mut value, "rules.1", Some(json!({ "z": 1 }))));
+        assert_eq!(value["rules"], json!([{ "y": 1 }, { "z": 1 }]));
+        assert!(assign(&mut value, "rules.0", None));
+        assert_eq!(value["rules"], json!([{ "z": 1 }]));
+        assert!(!assign(&mut value, "rules.5", Some(json!(1))));
+        assert!(!assign(&mut value, "rules.x.y", Some(json!(1))));
+        assert!(!assign(&mut value, "rules.3.y", Some(json!(1))));
+        assert_eq!(value["rules"], json!([{ "z": 1 }]));
+    }
+
+    #[test]
+    fn sets_and_removes_config_values() {
+        let mut config = Config {
+            icon_grid: true,
+            ..Config::default()
+        };
+        config.aliases.insert("firefox.desktop|firefox".to_string(), "火狐".to_string());
+
+        let updated = set_config_value(&config, "aria2.endpoint", "http://nas:6800/jsonrpc").unwrap();
+        assert_eq!(lookup(&config_value(&updated), "aria2.endpoint"), Some(&json!("http://nas:6800/jsonrpc")));
+
+        // null 删除设置过的项，有默认值的项恢复默认值
+        let updated = set_config_value(&config, "icon_grid", "null").unwrap();
+        assert!(!updated.icon_grid);
+        let updated = set_config_value(&config, "/aliases/firefox.desktop|firefox", "null").unwrap();
+        assert!(updated.aliases.is_empty());
+
+        // 数组中的条目按下标修改
+        let rule = r#"{ "match": { "hosts": ["a.com"] }, "target": { "browser": "edge" } }"#;
+        let updated = set_config_value(&config, "rules.0", rule).unwrap();
+        let updated = set_config_value(&updated, "rules.0.target", r#"{ "browser": "firefox" }"#).unwrap();
+        assert_eq!(updated.settings.rules[0].target, RouteTarget::Browser("firefox".to_string()));
+
+        assert_eq!(set_config_value(&config, "icon_gird", "true").err().unwrap().0, EXIT_NOT_FOUND);
+        assert_eq!(set_config_value(&config, "icon_gird", "null").err().unwrap().0, EXIT_NOT_FOUND);
+        assert_eq!(set_config_value(&config, "icon_grid", "\"大\"").err().unwrap().0, EXIT_USAGE);
+    }
+
+    #[test]
+    fn lists_browser_states() {
+        let mut config = Config::default();
+        config.settings.default_browser = Some("firefox.desktop|firefox".to_string());
+        config.aliases.insert("firefox.desktop|firefox".to_string(), "火狐".to_string());
+        let browser = Browser {
+            id: "firefox.desktop|firefox".to_string(),
+            name: "Firefox".to_string(),
+            command: "firefox {url}".to_string(),
+            hidden: true,
+            icon: None,
+            broken: None,
+        };
+        assert_eq!(browser_line(&config, &browser), "firefox.desktop|firefox\t火狐\t默认, 隐藏");
+    }
+}
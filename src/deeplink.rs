@@ -0,0 +1,265 @@
+use crate::actions::Pattern;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use url::Url;
+
+// 改写得到的应用链接
+#[derive(Clone, Debug, PartialEq)]
+pub struct AppLink {
+    pub app: String,
+    pub uri: String,
+}
+
+// 用户自定义改写：正则匹配完整URL，模板中可用 $1、${name} 引用捕获组
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeepLinkRewrite {
+    pub app: String,
+    pub pattern: Pattern,
+    pub template: String,
+}
+
+struct BuiltinRewrite {
+    app: &'static str,
+    rewrite: fn(&Url) -> Option<String>,
+}
+
+// 内置改写表
+const BUILTIN_REWRITES: &[BuiltinRewrite] = &[
+    BuiltinRewrite { app: "Spotify", rewrite: spotify },
+    BuiltinRewrite { app: "Zoom", rewrite: zoom },
+    BuiltinRewrite { app: "Microsoft Teams", rewrite: teams },
+    BuiltinRewrite { app: "Notion", rewrite: notion },
+    BuiltinRewrite { app: "腾讯会议", rewrite: tencent_meeting },
+    BuiltinRewrite { app: "飞书", rewrite: feishu },
+];
+
+fn path_segments(url: &Url) -> Vec<&str> {
+    url.path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+fn host_is(url: &Url, domain: &str) -> bool {
+    crate::actions::host_matches(url.host_str().unwrap_or(""), domain)
+}
+
+// open.spotify.com/track/ID -> spotify:track:ID（忽略 /intl-xx 前缀）
+fn spotify(url: &Url) -> Option<String> {
+    if url.host_str()? != "open.spotify.com" {
+        return None;
+    }
+    let segments: Vec<&str> = path_segments(url)
+        .into_iter()
+        .skip_while(|s| s.starts_with("intl-"))
+        .collect();
+    match segments.as_slice() {
+        [kind, id, ..]
+            if matches!(*kind, "track" | "album" | "artist" | "playlist" | "show" | "episode" | "user") =>
+        {
+            Some(format!("spotify:{}:{}", kind, id))
+        }
+        _ => None,
+    }
+}
+
+// zoom.us/j/123?pwd=x -> zoommtg://zoom.us/join?action=join&confno=123&pwd=x
+fn zoom(url: &Url) -> Option<String> {
+    if !host_is(url, "zoom.us") {
+        return None;
+    }
+    match path_segments(url).as_slice() {
+        ["j", confno, ..] => {
+            let mut uri = format!("zoommtg://zoom.us/join?action=join&confno={}", confno);
+            if let Some((_, pwd)) = url.query_pairs().find(|(k, _)| k == "pwd") {
+                uri.push_str(&format!("&pwd={}", urlencoding::encode(&pwd)));
+            }
+            Some(uri)
+        }
+        _ => None,
+    }
+}
+
+// teams.microsoft.com/l/... -> msteams:/l/...
+fn teams(url: &Url) -> Option<String> {
+    if url.host_str()? != "teams.microsoft.com" || !url.path().starts_with("/l/") {
+        return None;
+    }
+    let mut uri = format!("msteams:{}", url.path());
+    if let Some(query) = url.query() {
+        uri.push('?');
+        uri.push_str(query);
+    }
+    Some(uri)
+}
+
+// notion.so/... -> notion://www.notion.so/...
+fn notion(url: &Url) -> Option<String> {
+    if !host_is(url, "notion.so") || url.path() == "/" {
+        return None;
+    }
+    let mut uri = format!("notion://www.notion.so{}", url.path());
+    if let Some(query) = url.query() {
+        uri.push('?');
+        uri.push_str(query);
+    }
+    Some(uri)
+}
+
+// meeting.tencent.com/dm/ID -> wemeet://page/inmeeting?url=...
+fn tencent_meeting(url: &Url) -> Option<String> {
+    if url.host_str()? != "meeting.tencent.com" || !url.path().starts_with("/dm/") {
+        return None;
+    }
+    Some(format!(
+        "wemeet://page/inmeeting?url={}",
+        urlencoding::encode(url.as_str())
+    ))
+}
+
+// 飞书/Lark 文档 -> lark://applink.feishu.cn/client/docs/open?url=...
+fn feishu(url: &Url) -> Option<String> {
+    let applink = if host_is(url, "feishu.cn") {
+        "applink.feishu.cn"
+    } else if host_is(url, "larksuite.com") {
+        "applink.larksuite.com"
+    } else {
+        return None;
+    };
+    match path_segments(url).first() {
+        Some(&("docx" | "docs" | "doc" | "wiki" | "sheets" | "base" | "mindnotes" | "file")) => Some(format!(
+            "lark://{}/client/docs/open?url={}",
+            applink,
+            urlencoding::encode(url.as_str())
+        )),
+        _ => None,
+    }
+}
+
+// 查找链接对应的应用链接，自定义改写优先于内置改写
+pub fn rewrite(custom: &[DeepLinkRewrite], url: &str) -> Option<AppLink> {
+    for rule in custom {
+        if let Some(captures) = rule.pattern.regex().and_then(|regex| regex.captures(url)) {
+            let mut uri = String::new();
+            captures.expand(&rule.template, &mut uri);
+            return Some(AppLink {
+                app: rule.app.clone(),
+                uri,
+            });
+        }
+    }
+
+    let parsed = Url::parse(url).ok()?;
+    if parsed.scheme() != "https" && parsed.scheme() != "http" {
+        return None;
+    }
+    BUILTIN_REWRITES.iter().find_map(|builtin| {
+        (builtin.rewrite)(&parsed).map(|uri| AppLink {
+            app: builtin.app.to_string(),
+            uri,
+        })
+    })
+}
+
+// 交给系统的协议处理程序打开应用链接
+pub fn open_uri(uri: &str) -> std::io::Result<()> {
+    #[cfg(windows)]
+    let mut command = {
+        let mut command = Command::new("rundll32.exe");
+        command.arg("url.dll,FileProtocolHandler");
+        command
+    };
+    #[cfg(not(windows))]
+    let mut command = Command::new("xdg-open");
+
+    command.arg(uri).spawn()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uri(url: &str) -> Option<String> {
+        rewrite(&[], url).map(|link| link.uri)
+    }
+
+    #[test]
+    fn rewrites_spotify() {
+        assert_eq!(
+            uri("https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC?si=abc").as_deref(),
+            Some("spotify:track:4uLU6hMCjMI75M1A2tKUQC")
+        );
+        assert_eq!(
+            uri("https://open.spotify.com/intl-de/album/1DFixLWuPkv3KT3TnV35m3").as_deref(),
+            Some("spotify:album:1DFixLWuPkv3KT3TnV35m3")
+        );
+        assert_eq!(uri("https://open.spotify.com/"), None);
+    }
+
+    #[test]
+    fn rewrites_zoom() {
+        assert_eq!(
+            uri("https://us02web.zoom.us/j/85012345678?pwd=a1B2").as_deref(),
+            Some("zoommtg://zoom.us/join?action=join&confno=85012345678&pwd=a1B2")
+        );
+        assert_eq!(
+            uri("https://zoom.us/j/123").as_deref(),
+            Some("zoommtg://zoom.us/join?action=join&confno=123")
+        );
+        assert_eq!(uri("https://zoom.us/pricing"), None);
+    }
+
+    #[test]
+    fn rewrites_teams() {
+        assert_eq!(
+            uri("https://teams.microsoft.com/l/meetup-join/19%3ameeting_x%40thread.v2/0?context=%7b%7d").as_deref(),
+            Some("msteams:/l/meetup-join/19%3ameeting_x%40thread.v2/0?context=%7b%7d")
+        );
+        assert_eq!(uri("https://teams.microsoft.com/v2/"), None);
+    }
+
+    #[test]
+    fn rewrites_notion() {
+        assert_eq!(
+            uri("https://www.notion.so/team/Page-0123456789abcdef?pvs=4").as_deref(),
+            Some("notion://www.notion.so/team/Page-0123456789abcdef?pvs=4")
+        );
+        assert_eq!(uri("https://notion.so/"), None);
+    }
+
+    #[test]
+    fn rewrites_tencent_meeting() {
+        assert_eq!(
+            uri("https://meeting.tencent.com/dm/AbCdEf").as_deref(),
+            Some("wemeet://page/inmeeting?url=https%3A%2F%2Fmeeting.tencent.com%2Fdm%2FAbCdEf")
+        );
+    }
+
+    #[test]
+    fn rewrites_feishu_docs() {
+        let link = rewrite(&[], "https://example.feishu.cn/docx/AbC123").unwrap();
+        assert_eq!(link.app, "飞书");
+        assert_eq!(
+            link.uri,
+            "lark://applink.feishu.cn/client/docs/open?url=https%3A%2F%2Fexample.feishu.cn%2Fdocx%2FAbC123"
+        );
+        assert_eq!(uri("https://www.feishu.cn/product"), None);
+    }
+
+    #[test]
+    fn custom_rewrites_take_precedence() {
+        let custom = vec![DeepLinkRewrite {
+            app: "Slack".to_string(),
+            pattern: Pattern::new(r"^https://app\.slack\.com/client/(\w+)/(\w+)"),
+            template: "slack://channel?team=$1&id=$2".to_string(),
+        }];
+        assert_eq!(
+            rewrite(&custom, "https://app.slack.com/client/T123/C456"),
+            Some(AppLink {
+                app: "Slack".to_string(),
+                uri: "slack://channel?team=T123&id=C456".to_string(),
+            })
+        );
+        assert_eq!(rewrite(&custom, "https://example.com/"), None);
+    }
+}
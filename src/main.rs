@@ -1,25 +1,76 @@
 #![windows_subsystem = "windows"]
 
+mod actions;
+mod aria2;
+mod cli;
+mod deeplink;
+mod media;
+mod ordering;
+mod paths;
+mod platform;
+mod portable;
+mod policy;
+mod probe;
+mod schema;
+mod sharing;
+mod storage;
+
+use actions::{Action, RouteTarget, RoutingRule};
+use aria2::Aria2Config;
+use deeplink::DeepLinkRewrite;
+use media::MediaConfig;
+use ordering::{BrowserUsage, SortMode};
+use probe::HeadInfo;
 use eframe::{egui, NativeOptions};
-use is_elevated::is_elevated;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use winreg::enums::*;
-use winreg::RegKey;
 use url::Url;
 
+// 提取链接使用的方式
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Extractor {
+    // 不是拦截页面，链接保持原样
+    None,
+    WeChat,
+    WeWork,
+    Qq,
+    // 通用的 url、link 等参数
+    QueryParam,
+    Fragment,
+}
+
+impl Extractor {
+    fn name(self) -> &'static str {
+        match self {
+            Extractor::None => "none",
+            Extractor::WeChat => "wechat",
+            Extractor::WeWork => "wework",
+            Extractor::Qq => "qq",
+            Extractor::QueryParam => "query",
+            Extractor::Fragment => "fragment",
+        }
+    }
+}
+
 // 从安全中心拦截的URL中提取真正的链接（同步版本，不处理微信）
 fn extract_real_url_sync(input_url: &str) -> (String, bool) {
+    let (url, extractor) = extract_with_extractor(input_url);
+    (url, extractor == Extractor::WeChat)
+}
+
+// 同步提取并返回使用的方式；微信拦截页面返回原链接，需要再访问页面提取
+fn extract_with_extractor(input_url: &str) -> (String, Extractor) {
     // 尝试解析URL
     if let Ok(parsed_url) = Url::parse(input_url) {
         let host = parsed_url.host_str().unwrap_or("");
         
         // 微信拦截页面需要异步处理
         if host.contains("weixin110.qq.com") {
-            return (input_url.to_string(), true); // 返回原URL和需要异步处理的标志
+            return (input_url.to_string(), Extractor::WeChat); // 返回原URL，需要异步处理
         }
         
         // 企业微信拦截页面处理
@@ -28,9 +79,9 @@ fn extract_real_url_sync(input_url: &str) -> (String, bool) {
              if let Some(uri) = query_pairs.get("uri") {
                  let decoded_uri = urlencoding::decode(uri).unwrap_or_else(|_| uri.clone());
                  if !decoded_uri.starts_with("http://") && !decoded_uri.starts_with("https://") {
-                     return (format!("https://{}", decoded_uri), false);
+                     return (format!("https://{}", decoded_uri), Extractor::WeWork);
                  }
-                 return (decoded_uri.to_string(), false);
+                 return (decoded_uri.to_string(), Extractor::WeWork);
              }
          }
          
@@ -40,7 +91,7 @@ fn extract_real_url_sync(input_url: &str) -> (String, bool) {
              if let Some(url_param) = query_pairs.get("url") {
                  let decoded_url = urlencoding::decode(url_param).unwrap_or_else(|_| url_param.clone());
                  if decoded_url.starts_with("http://") || decoded_url.starts_with("https://") {
-                     return (decoded_url.to_string(), false);
+                     return (decoded_url.to_string(), Extractor::Qq);
                  }
              }
          }
@@ -53,7 +104,7 @@ fn extract_real_url_sync(input_url: &str) -> (String, bool) {
             if let Some(extracted_url) = query_pairs.get(*param) {
                 let decoded_url = urlencoding::decode(extracted_url).unwrap_or_else(|_| extracted_url.clone());
                 if decoded_url.starts_with("http://") || decoded_url.starts_with("https://") {
-                     return (decoded_url.to_string(), false);
+                     return (decoded_url.to_string(), Extractor::QueryParam);
                  }
              }
          }
@@ -61,13 +112,13 @@ fn extract_real_url_sync(input_url: &str) -> (String, bool) {
          // 检查fragment部分
          if let Some(fragment) = parsed_url.fragment() {
              if fragment.starts_with("http://") || fragment.starts_with("https://") {
-                 return (fragment.to_string(), false);
+                 return (fragment.to_string(), Extractor::Fragment);
              }
          }
      }
      
      // 如果无法提取，返回原始URL
-     (input_url.to_string(), false)
+     (input_url.to_string(), Extractor::None)
  }
 
 // 从微信拦截页面提取真实链接
@@ -101,74 +152,793 @@ fn extract_from_wechat_page(wechat_url: &str) -> Option<String> {
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 struct Browser {
+    // 稳定ID：注册表子项名或 .desktop 文件ID加可执行文件路径，不随显示名称变化
+    #[serde(default)]
+    id: String,
     name: String,
     command: String,
     #[serde(default)]
     hidden: bool,
+    // 图标位置：Windows 为 "路径,索引"，Linux 为 .desktop 的 Icon= 值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    icon: Option<String>,
+    // 程序无法启动的原因，例如卸载后残留的注册项
+    #[serde(skip)]
+    broken: Option<String>,
+}
+
+#[cfg(test)]
+impl Browser {
+    // 测试用的浏览器，只有ID和名称
+    fn new(id: &str, name: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            command: String::new(),
+            hidden: false,
+            icon: None,
+            broken: None,
+        }
+    }
+}
+
+// 浏览器来源：Windows注册表、Linux .desktop 文件等
+trait BrowserSource {
+    fn browsers(&self) -> Vec<Browser>;
+}
+
+// 浏览器分组（例如“跨浏览器测试”），成员为浏览器ID
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct BrowserGroup {
+    name: String,
+    browsers: Vec<String>,
+}
+
+// 随配置方案切换的设置；顶层的这些字段是不属于任何方案时使用的“默认”方案
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct ProfileSettings {
+    // 隐藏的浏览器ID；旧版本保存的是显示名称，加载时自动迁移
+    #[serde(default)]
+    hidden_browsers: Vec<String>,
+    #[serde(default)]
+    rules: Vec<RoutingRule>,
+    // 默认浏览器ID：排在选择窗口最前面，按回车直接打开
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default_browser: Option<String>,
+    // 不访问网络提取链接（微信页面、HEAD 检查）
+    #[serde(default)]
+    disable_network_extraction: bool,
 }
 
 #[derive(Serialize, Deserialize, Default)]
 struct Config {
-    hidden_browsers: Vec<String>,
+    #[serde(flatten)]
+    settings: ProfileSettings,
+    // 命名的配置方案，例如“工作”“个人”
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    profiles: BTreeMap<String, ProfileSettings>,
+    // 当前使用的方案；None 时使用顶层的默认方案
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    active_profile: Option<String>,
+    // 命令行 --profile 指定的方案，只对本次运行有效
+    #[serde(skip)]
+    profile_override: Option<String>,
+    #[serde(default)]
+    browser_groups: Vec<BrowserGroup>,
+    #[serde(default)]
+    actions: Vec<Action>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    aria2: Option<Aria2Config>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    media: Option<MediaConfig>,
+    #[serde(default)]
+    deep_links: Vec<DeepLinkRewrite>,
+    // 以只有图标的网格显示浏览器，适合安装了很多浏览器的情况
+    #[serde(default)]
+    icon_grid: bool,
+    // 不在选择窗口中显示无法启动的浏览器
+    #[serde(default)]
+    auto_hide_broken: bool,
+    // 扫描便携版浏览器的目录，例如共享盘上的 Chrome/Firefox 便携版
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    search_dirs: Vec<PathBuf>,
+    // 浏览器ID的手动排列顺序，在设置中拖动调整
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    browser_order: Vec<String>,
+    // 浏览器ID -> 显示名称，例如 "Chrome (工作)"
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    aliases: BTreeMap<String, String>,
+    #[serde(default)]
+    sort_mode: SortMode,
+    // 浏览器ID -> 使用次数和最近使用时间
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    usage: BTreeMap<String, BrowserUsage>,
+    // 只读的团队预设文件，其中的条目排在用户自己的配置之后
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    team_preset: Option<PathBuf>,
+    // 已叠加的团队预设内容，保存时据此去掉预设提供的条目
+    #[serde(skip)]
+    preset: Option<serde_json::Value>,
+    #[serde(skip)]
+    preset_error: Option<String>,
+    // 管理员策略，不写入配置文件
+    #[serde(skip)]
+    policy: policy::Policy,
+    #[serde(skip)]
+    policy_error: Option<String>,
 }
 
-fn get_config_path() -> PathBuf {
-    let mut path = std::env::temp_dir();
-    path.push("browser_selector_config.json");
-    path
+impl Config {
+    // 浏览器的显示名称：有别名时使用别名
+    fn display_name<'a>(&'a self, browser: &'a Browser) -> &'a str {
+        match self.aliases.get(&browser.id) {
+            Some(alias) if !alias.trim().is_empty() => alias,
+            _ => &browser.name,
+        }
+    }
+
+    // 浏览器是否显示在选择窗口中
+    fn shows(&self, browser: &Browser) -> bool {
+        let auto_hidden = self.auto_hide_broken && browser.broken.is_some();
+        !browser.hidden && !auto_hidden && !self.policy.blocks(browser)
+    }
+
+    // 本次运行使用的方案名称：命令行指定的优先，都不存在时为默认方案
+    fn profile_name(&self) -> Option<&str> {
+        [self.profile_override.as_deref(), self.active_profile.as_deref()]
+            .into_iter()
+            .flatten()
+            .find(|name| self.profiles.contains_key(*name))
+    }
+
+    fn profile(&self) -> &ProfileSettings {
+        match self.profile_name() {
+            Some(name) => &self.profiles[name],
+            None => &self.settings,
+        }
+    }
+
+    fn profile_mut(&mut self) -> &mut ProfileSettings {
+        match self.profile_name().map(str::to_string) {
+            Some(name) => self.profiles.get_mut(&name).unwrap_or(&mut self.settings),
+            None => &mut self.settings,
+        }
+    }
+
+    // 是否可以访问网络提取链接，管理员策略优先
+    fn network_extraction_allowed(&self) -> bool {
+        !self
+            .policy
+            .disable_network_extraction
+            .unwrap_or(self.profile().disable_network_extraction)
+    }
+
+    // 分组中当前已安装且未被禁止的成员，按分组中的顺序；卸载的浏览器仍留在分组里，重新安装后恢复
+    fn group_members<'a>(&self, group: &BrowserGroup, browsers: &'a [Browser]) -> Vec<&'a Browser> {
+        group
+            .browsers
+            .iter()
+            .filter_map(|id| browsers.iter().find(|b| &b.id == id))
+            .filter(|b| !self.policy.blocks(b))
+            .collect()
+    }
+
+    // 把选中的浏览器保存为分组，替换同名的分组
+    fn save_group(&mut self, name: &str, browsers: &[&Browser]) {
+        let group = BrowserGroup {
+            name: name.trim().to_string(),
+            browsers: browsers.iter().map(|b| b.id.clone()).collect(),
+        };
+        self.browser_groups.retain(|g| g.name != group.name);
+        self.browser_groups.push(group);
+    }
+
+    // 动作、路由规则和应用链接改写中无法编译的正则表达式，作为配置错误提示
+    fn pattern_error(&self) -> Option<String> {
+        let rules = std::iter::once(&self.settings)
+            .chain(self.profiles.values())
+            .flat_map(|profile| profile.rules.iter())
+            .chain(self.policy.rules.iter());
+        let errors: Vec<String> = self
+            .actions
+            .iter()
+            .map(|action| &action.matches)
+            .chain(rules.map(|rule| &rule.matches))
+            .filter_map(|matches| matches.pattern.as_ref())
+            .chain(self.deep_links.iter().map(|rule| &rule.pattern))
+            .filter_map(|pattern| pattern.error())
+            .collect();
+        if errors.is_empty() {
+            None
+        } else {
+            Some(errors.join("; "))
+        }
+    }
 }
 
-fn load_config() -> Config {
-    fs::read_to_string(get_config_path())
-        .ok()
-        .and_then(|content| serde_json::from_str(&content).ok())
+// 写入文件的配置：带上格式版本
+#[derive(Serialize)]
+struct VersionedConfig<'a> {
+    version: u64,
+    #[serde(flatten)]
+    config: &'a Config,
+}
+
+// 解析配置文件：先升级到当前格式，再按当前格式严格解析，不认识的配置项也算错误；返回配置和文件原来的版本
+fn parse_config(content: &str) -> Result<(Config, u64), String> {
+    let mut value: serde_json::Value =
+        serde_json::from_str(content).map_err(|e| format!("配置文件不是有效的 JSON: {}", e))?;
+    let version = schema::upgrade(&mut value)?;
+    let config: Config = serde_json::from_value(value.clone()).map_err(|e| format!("配置文件内容无效: {}", e))?;
+    let parsed = serde_json::to_value(VersionedConfig { version: schema::CURRENT_VERSION, config: &config })
+        .map_err(|e| e.to_string())?;
+    let unknown = schema::unknown_keys(&value, &parsed);
+    if !unknown.is_empty() {
+        return Err(format!("配置文件中有未知的配置项: {}", unknown.join(", ")));
+    }
+    Ok((config, version))
+}
+
+// 设置中显示的团队预设路径
+fn team_preset_input(config: &Config) -> String {
+    config
+        .team_preset
+        .as_ref()
+        .map(|path| path.display().to_string())
         .unwrap_or_default()
 }
 
+// 检查配置文件是否被外部修改的间隔
+const CONFIG_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+// 保留的配置备份数量：config.json.1（最新）到 config.json.5
+const CONFIG_BACKUPS: usize = 5;
+
+// 本进程上次读取或写入的各配置文件的内容，保存时据此合并其他窗口在此期间写入的修改
+static CONFIG_BASE: std::sync::Mutex<BTreeMap<PathBuf, serde_json::Value>> = std::sync::Mutex::new(BTreeMap::new());
+
+// 用户自己的配置内容，不含团队预设提供的条目
+fn config_value(config: &Config) -> serde_json::Value {
+    let value = serde_json::to_value(VersionedConfig {
+        version: schema::CURRENT_VERSION,
+        config,
+    })
+    .unwrap_or_default();
+    match &config.preset {
+        Some(preset) => sharing::strip_preset(&value, preset),
+        None => value,
+    }
+}
+
+// 读取导入文件或团队预设，升级到当前格式；这类文件可以只包含部分配置
+fn read_config_layer(path: &Path) -> Result<serde_json::Value, String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut value = serde_json::from_str(&content).map_err(|e| format!("不是有效的 JSON: {}", e))?;
+    schema::upgrade(&mut value)?;
+    Ok(value)
+}
+
+// 把团队预设叠加到用户配置下面；预设文件无法读取时只记录错误，继续使用用户自己的配置
+fn apply_team_preset(mut config: Config) -> Config {
+    let Some(path) = config.team_preset.clone() else {
+        return config;
+    };
+    let preset = match read_config_layer(&path) {
+        Ok(preset) => preset,
+        Err(e) => {
+            config.preset_error = Some(format!("无法读取团队预设 {}: {}", path.display(), e));
+            return config;
+        }
+    };
+    match serde_json::from_value::<Config>(sharing::layer(&config_value(&config), &preset)) {
+        Ok(mut layered) => {
+            layered.preset = Some(preset);
+            layered
+        }
+        Err(e) => {
+            config.preset_error = Some(format!("团队预设 {} 内容无效: {}", path.display(), e));
+            config
+        }
+    }
+}
+
+fn set_config_base(path: &Path, config: &Config) {
+    if let Ok(mut base) = CONFIG_BASE.lock() {
+        base.insert(path.to_path_buf(), config_value(config));
+    }
+}
+
+// 读取管理员策略；策略无法读取时记录错误，用户设置照常生效
+fn apply_policy(config: &mut Config, source: &dyn policy::PolicySource) {
+    match source.policy() {
+        Ok(policy) => {
+            config.policy = policy;
+            config.policy_error = None;
+        }
+        Err(e) => {
+            config.policy = policy::Policy::default();
+            config.policy_error = Some(e);
+        }
+    }
+}
+
+// 读取配置，叠加团队预设和管理员策略
+fn load_config() -> (Config, Option<String>) {
+    let path = paths::config_file();
+    if let Some(legacy) = paths::legacy_config_file() {
+        paths::migrate_legacy_config(&legacy, &path).ok();
+    }
+    load_config_from(&path, platform::current().policy_source().as_ref())
+}
+
+fn load_config_from(path: &Path, policy_source: &dyn policy::PolicySource) -> (Config, Option<String>) {
+    let (config, error) = load_own_config(path);
+    let mut config = apply_team_preset(config);
+    apply_policy(&mut config, policy_source);
+    (config, error)
+}
+
+// 读取用户自己的配置，文件不存在时为默认配置；无法解析时把原文件备份为 config.json.invalid，
+// 改用最近一个有效的备份，并一起返回错误原因
+fn load_own_config(path: &Path) -> (Config, Option<String>) {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return (Config::default(), None),
+        Err(e) => return (Config::default(), Some(format!("无法读取配置文件 {}: {}", path.display(), e))),
+    };
+
+    match parse_config(&content) {
+        Ok((config, version)) => {
+            set_config_base(path, &config);
+            // 升级后的配置写回文件，旧文件保留为 config.json.v<版本>
+            let upgraded_backup = paths::config_backup(path, &format!("v{}", version));
+            if version < schema::CURRENT_VERSION && fs::copy(path, upgraded_backup).is_ok() {
+                if let Err(e) = write_config_to(path, &config) {
+                    eprintln!("保存配置失败: {}", e);
+                }
+            }
+            (config, None)
+        }
+        Err(e) => {
+            let invalid = paths::config_backup(path, "invalid");
+            fs::copy(path, &invalid).ok();
+            let restored = storage::backups(path, CONFIG_BACKUPS).into_iter().find_map(|backup| {
+                let content = fs::read_to_string(&backup).ok()?;
+                parse_config(&content).ok().map(|(config, _)| (config, backup))
+            });
+            let (config, fallback) = match restored {
+                Some((config, backup)) => (config, format!("已改用备份 {}", backup.display())),
+                None => (Config::default(), "当前使用默认配置".to_string()),
+            };
+            let error = format!("{}（原文件已备份到 {}，{}）", e, invalid.display(), fallback);
+            (config, Some(error))
+        }
+    }
+}
+
+// 文件的修改时间和大小，文件不存在时为 None
+type FileStamp = Option<(std::time::SystemTime, u64)>;
+
+fn file_stamp(path: &Path) -> FileStamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+// 配置文件以及叠加在上面的团队预设、管理员策略文件
+#[derive(Clone)]
+struct ConfigFiles {
+    config: PathBuf,
+    layers: Vec<PathBuf>,
+}
+
+impl ConfigFiles {
+    fn of(config: &Config) -> Self {
+        let policy_source = platform::current().policy_source();
+        Self {
+            config: paths::config_file(),
+            layers: config
+                .team_preset
+                .iter()
+                .map(PathBuf::as_path)
+                .chain(policy_source.path())
+                .map(Path::to_path_buf)
+                .collect(),
+        }
+    }
+
+    fn stamp(&self) -> ConfigStamp {
+        ConfigStamp {
+            config: file_stamp(&self.config),
+            layers: self.layers.iter().map(|path| file_stamp(path)).collect(),
+        }
+    }
+}
+
+// 上述文件的状态，用于发现其他程序或手工编辑的修改
+#[derive(PartialEq)]
+struct ConfigStamp {
+    config: FileStamp,
+    layers: Vec<FileStamp>,
+}
+
+// 配置文件存在且状态有变化时需要重新读取，返回团队预设或策略文件是否也变了
+fn reload_needed(previous: &ConfigStamp, current: &ConfigStamp) -> Option<bool> {
+    if current.config.is_none() || current == previous {
+        return None;
+    }
+    Some(current.layers != previous.layers)
+}
+
+// 在后台线程里定期检查配置文件，有变化时才唤醒窗口，窗口空闲时不需要定时重绘
+struct ConfigWatcher {
+    files: std::sync::Arc<std::sync::Mutex<ConfigFiles>>,
+    changed: std::sync::Arc<std::sync::atomic::AtomicBool>,
+}
+
+impl ConfigWatcher {
+    fn start(ctx: egui::Context, files: ConfigFiles) -> Self {
+        let watcher = Self {
+            files: std::sync::Arc::new(std::sync::Mutex::new(files)),
+            changed: Default::default(),
+        };
+        let files = watcher.files.clone();
+        let changed = watcher.changed.clone();
+        std::thread::spawn(move || {
+            let stamp = |files: &std::sync::Mutex<ConfigFiles>| match files.lock() {
+                Ok(files) => files.stamp(),
+                Err(e) => e.into_inner().stamp(),
+            };
+            let mut last = stamp(&files);
+            loop {
+                std::thread::sleep(CONFIG_CHECK_INTERVAL);
+                let current = stamp(&files);
+                if current != last {
+                    last = current;
+                    changed.store(true, std::sync::atomic::Ordering::SeqCst);
+                    ctx.request_repaint();
+                }
+            }
+        });
+        watcher
+    }
+
+    // 团队预设改变后换成新的文件列表
+    fn watch(&self, files: ConfigFiles) {
+        match self.files.lock() {
+            Ok(mut current) => *current = files,
+            Err(e) => *e.into_inner() = files,
+        }
+    }
+
+    fn take_changed(&self) -> bool {
+        self.changed.swap(false, std::sync::atomic::Ordering::SeqCst)
+    }
+}
+
+// 重新读取被外部修改的配置文件；文件无效时不做任何改动，由调用方保留当前配置
+fn reload_config() -> Result<Config, String> {
+    reload_config_from(&paths::config_file(), platform::current().policy_source().as_ref())
+}
+
+fn reload_config_from(path: &Path, policy_source: &dyn policy::PolicySource) -> Result<Config, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("无法读取配置文件: {}", e))?;
+    let (config, _) = parse_config(&content)?;
+    set_config_base(path, &config);
+    let mut config = apply_team_preset(config);
+    apply_policy(&mut config, policy_source);
+    Ok(config)
+}
+
+fn write_config(config: &Config) -> Result<(), String> {
+    write_config_to(&paths::config_file(), config)
+}
+
+// 保存配置：加锁后重新读取文件，合并其他窗口的修改，再原子地替换文件并轮换备份
+fn write_config_to(path: &Path, config: &Config) -> Result<(), String> {
+    let _lock = storage::FileLock::acquire(path).map_err(|e| format!("无法锁定配置文件: {}", e))?;
+    let mut bases = CONFIG_BASE.lock().map_err(|e| e.to_string())?;
+    let base = bases.get(path).cloned();
+
+    let ours = config_value(config);
+    let on_disk = fs::read_to_string(path)
+        .ok()
+        .and_then(|content| parse_config(&content).ok());
+    let merged = match &on_disk {
+        Some((theirs, version)) => {
+            let theirs = config_value(theirs);
+            let base_value = base.unwrap_or_else(|| config_value(&Config::default()));
+            let merged = storage::merge3(&base_value, &ours, &theirs);
+            // 内容没有变化且文件已是当前版本时不必重写
+            if merged == theirs && *version == schema::CURRENT_VERSION {
+                bases.insert(path.to_path_buf(), ours);
+                return Ok(());
+            }
+            merged
+        }
+        None => ours.clone(),
+    };
+
+    // 转回 Config 再序列化，保持字段顺序
+    let merged = serde_json::from_value::<Config>(merged).map_err(|e| format!("合并后的配置无效: {}", e))?;
+    let versioned = VersionedConfig {
+        version: schema::CURRENT_VERSION,
+        config: &merged,
+    };
+    let content = serde_json::to_string_pretty(&versioned).map_err(|e| e.to_string())?;
+    // 只备份能正常读取的版本
+    if on_disk.is_some() {
+        storage::rotate_backups(path, CONFIG_BACKUPS).ok();
+    }
+    storage::write_atomic(path, content.as_bytes()).map_err(|e| format!("无法写入 {}: {}", path.display(), e))?;
+    bases.insert(path.to_path_buf(), ours);
+    Ok(())
+}
+
 fn save_config(config: &Config) {
-    if let Ok(content) = serde_json::to_string_pretty(config) {
-        fs::write(get_config_path(), content).ok();
-    }
-}
-
-fn get_browsers_from_hive(hive: &RegKey, browsers: &mut Vec<Browser>) {
-    if let Ok(key) = hive.open_subkey("SOFTWARE\\Clients\\StartMenuInternet") {
-        for subkey_name in key.enum_keys().filter_map(Result::ok) {
-            if let Ok(subkey) = key.open_subkey(&subkey_name) {
-                if let Ok(name) = subkey.get_value::<String, _>("") {
-                    if let Ok(command_key) = subkey.open_subkey("shell\\open\\command") {
-                        if let Ok(command) = command_key.get_value::<String, _>("") {
-                            if !browsers.iter().any(|b| b.name == name) {
-                                browsers.push(Browser {
-                                    name,
-                                    command,
-                                    hidden: false,
-                                });
-                            }
-                        }
-                    }
+    if let Err(e) = write_config(config) {
+        eprintln!("保存配置失败: {}", e);
+    }
+}
+
+// 命令中的可执行文件路径：带引号时取引号内的部分，否则取第一个空格前的部分
+fn command_executable(command: &str) -> &str {
+    let command = command.trim();
+    if let Some(quoted) = command.strip_prefix('"') {
+        // 处理带引号的路径
+        if let Some(end_quote) = quoted.find('"') {
+            &quoted[..end_quote]
+        } else {
+            command
+        }
+    } else {
+        // 处理不带引号的路径，取第一个空格前的部分
+        command.split_whitespace().next().unwrap_or(command)
+    }
+}
+
+// 浏览器ID：来源中的标识（注册表子项名、.desktop 文件ID）加可执行文件路径
+fn browser_id(source_key: &str, command: &str) -> String {
+    format!("{}|{}", source_key, command_executable(command))
+}
+
+// 把按显示名称保存的条目替换为对应浏览器的ID，同名的浏览器都会被替换；返回是否有改动
+fn migrate_browser_names(entries: &mut Vec<String>, browsers: &[Browser]) -> bool {
+    let mut migrated = Vec::new();
+    for entry in entries.iter() {
+        let ids: Vec<&String> = if browsers.iter().any(|b| &b.id == entry) {
+            vec![entry]
+        } else {
+            let same_name: Vec<&String> = browsers.iter().filter(|b| &b.name == entry).map(|b| &b.id).collect();
+            if same_name.is_empty() {
+                // 未安装的浏览器保持原样，重新安装后再迁移
+                vec![entry]
+            } else {
+                same_name
+            }
+        };
+        for id in ids {
+            if !migrated.contains(id) {
+                migrated.push(id.clone());
+            }
+        }
+    }
+    let changed = migrated != *entries;
+    *entries = migrated;
+    changed
+}
+
+// 迁移隐藏列表和分组成员
+fn migrate_config(config: &mut Config, browsers: &[Browser]) -> bool {
+    let mut changed = migrate_browser_names(&mut config.settings.hidden_browsers, browsers);
+    for profile in config.profiles.values_mut() {
+        changed |= migrate_browser_names(&mut profile.hidden_browsers, browsers);
+    }
+    for group in &mut config.browser_groups {
+        changed |= migrate_browser_names(&mut group.browsers, browsers);
+    }
+    // 团队预设中的名称也换成同样的ID，保存时才能认出并去掉预设提供的条目
+    if let Some(preset) = &mut config.preset {
+        migrate_preset_names(preset.get_mut("hidden_browsers"), browsers);
+        if let Some(groups) = preset.get_mut("browser_groups").and_then(serde_json::Value::as_array_mut) {
+            for group in groups {
+                migrate_preset_names(group.get_mut("browsers"), browsers);
+            }
+        }
+    }
+    changed
+}
+
+fn migrate_preset_names(entries: Option<&mut serde_json::Value>, browsers: &[Browser]) {
+    let Some(entries) = entries else {
+        return;
+    };
+    if let Ok(mut names) = serde_json::from_value::<Vec<String>>(entries.clone()) {
+        if migrate_browser_names(&mut names, browsers) {
+            *entries = serde_json::Value::from(names);
+        }
+    }
+}
+
+// 浏览器的隐私模式参数，按可执行文件名判断；不认识的浏览器返回 None
+fn private_flag(browser: &Browser) -> Option<&'static str> {
+    // Windows 路径按 \ 分隔，在其他平台上读取的配置中也可能出现
+    let executable = command_executable(&browser.command).to_lowercase();
+    let file_name = executable.rsplit(['\\', '/']).next()?;
+    match file_name.strip_suffix(".exe").unwrap_or(file_name) {
+        "firefox" | "firefox-esr" | "librewolf" | "waterfox" => Some("--private-window"),
+        "msedge" | "microsoft-edge" | "microsoft-edge-stable" => Some("--inprivate"),
+        "opera" => Some("--private"),
+        "chrome" | "google-chrome" | "google-chrome-stable" | "chromium" | "chromium-browser" | "brave"
+        | "brave-browser" | "vivaldi" | "vivaldi-stable" => Some("--incognito"),
+        _ => None,
+    }
+}
+
+// 按注册表中的命令启动浏览器，只取可执行文件路径并把URL作为参数传入；private 时以隐私模式打开
+fn launch_browser(browser: &Browser, url: &str, private: bool) -> std::io::Result<()> {
+    // 不知道怎样打开隐私窗口时报错，而不是以普通模式打开
+    let flag = match private {
+        true => Some(private_flag(browser).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::Unsupported, "不知道如何以隐私模式启动这个浏览器")
+        })?),
+        false => None,
+    };
+
+    // .desktop 的 Exec 已转换为带 {url} 的命令模板，按完整参数启动
+    if browser.command.contains("{url}") {
+        let mut args = actions::expand_template(&browser.command, url);
+        if let Some(flag) = flag {
+            args.insert(1.min(args.len()), flag.to_string());
+        }
+        return actions::spawn_args(&args);
+    }
+
+    let executable = command_executable(&browser.command);
+    if executable.is_empty() {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "浏览器命令为空"));
+    }
+    Command::new(executable).args(flag).arg(url).spawn()?;
+    Ok(())
+}
+
+// 按浏览器ID、名称或别名查找
+fn find_browser<'a>(config: &Config, browsers: &[&'a Browser], name: &str) -> Option<&'a Browser> {
+    browsers
+        .iter()
+        .find(|b| b.id == name || b.name == name || config.display_name(b) == name)
+        .copied()
+}
+
+// 可以立即按规则路由的链接；微信拦截页面要先联网提取真实链接，由选择窗口提取后再路由
+fn routing_url(url: &str) -> Option<String> {
+    let (extracted, needs_async) = extract_real_url_sync(url);
+    (!needs_async).then_some(extracted)
+}
+
+// 按路由规则直接打开链接，返回是否已处理
+fn apply_routing(config: &Config, all_browsers: &[Browser], url: &str, private: bool) -> bool {
+    // 管理员策略中的规则优先
+    let rules: Vec<RoutingRule> = config.policy.rules.iter().chain(&config.profile().rules).cloned().collect();
+    let browsers: Vec<&Browser> = all_browsers.iter().filter(|b| !config.policy.blocks(b)).collect();
+    let result = match actions::find_route(&rules, url) {
+        // 规则中可以写浏览器ID、名称或别名
+        Some(RouteTarget::Browser(name)) => match find_browser(config, &browsers, name) {
+            Some(browser) => launch_browser(browser, url, private),
+            None => return false,
+        },
+        Some(RouteTarget::Action(name)) => match config.actions.iter().find(|a| &a.name == name) {
+            Some(action) => actions::run_action(action, url),
+            None => return false,
+        },
+        Some(RouteTarget::Group(name)) => {
+            let members = config
+                .browser_groups
+                .iter()
+                .find(|g| &g.name == name)
+                .map(|g| config.group_members(g, all_browsers))
+                .unwrap_or_default();
+            if members.is_empty() {
+                return false;
+            }
+            for browser in members {
+                if let Err(e) = launch_browser(browser, url, private) {
+                    eprintln!("启动浏览器失败 ({}): {}", browser.name, e);
                 }
             }
+            Ok(())
         }
+        None => return false,
+    };
+    if let Err(e) = result {
+        // 目标启动失败时仍显示选择窗口
+        eprintln!("按路由规则打开失败: {}", e);
+        return false;
     }
+    true
 }
 
-fn get_installed_browsers() -> Vec<Browser> {
-    let mut browsers = Vec::new();
-    let config = load_config();
+// 上次发现的系统浏览器，来源的状态标记不变时直接使用
+#[derive(Serialize, Deserialize)]
+struct DiscoveryCache {
+    stamp: String,
+    browsers: Vec<Browser>,
+}
 
-    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
-    get_browsers_from_hive(&hklm, &mut browsers);
+// 注册表或 .desktop 文件中的浏览器；use_cache 为 false 时总是重新扫描
+fn discover_system_browsers(use_cache: bool) -> Vec<Browser> {
+    let platform = platform::current();
+    let stamp = platform.discovery_stamp();
 
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    get_browsers_from_hive(&hkcu, &mut browsers);
+    if use_cache {
+        let cached = fs::read_to_string(paths::cache_file())
+            .ok()
+            .and_then(|content| serde_json::from_str::<DiscoveryCache>(&content).ok());
+        if let (Some(stamp), Some(cache)) = (&stamp, cached) {
+            if &cache.stamp == stamp {
+                return cache.browsers;
+            }
+        }
+    }
 
-    for browser in &mut browsers {
-        if config.hidden_browsers.contains(&browser.name) {
-            browser.hidden = true;
+    let mut browsers: Vec<Browser> = Vec::new();
+    for source in platform.browser_sources() {
+        for browser in source.browsers() {
+            if !browsers.iter().any(|b| b.id == browser.id) {
+                browsers.push(browser);
+            }
         }
     }
 
+    if let Some(stamp) = stamp {
+        let cache = DiscoveryCache { stamp, browsers };
+        if let Ok(content) = serde_json::to_string(&cache) {
+            storage::write_atomic(&paths::cache_file(), content.as_bytes()).ok();
+        }
+        return cache.browsers;
+    }
+    browsers
+}
+
+// 系统浏览器加上便携版浏览器，并检查程序是否还能启动
+fn discover_browsers(search_dirs: &[PathBuf], use_cache: bool) -> Vec<Browser> {
+    let mut browsers = discover_system_browsers(use_cache);
+
+    // 便携版浏览器不缓存：搜索目录可能在共享盘上，修改时间不可靠。跳过已经注册到系统的同一程序
+    let portable = portable::PortableBrowserSource {
+        dirs: search_dirs.to_vec(),
+    };
+    for browser in portable.browsers() {
+        let executable = command_executable(&browser.command);
+        let registered = browsers
+            .iter()
+            .any(|b| b.id == browser.id || Path::new(command_executable(&b.command)) == Path::new(executable));
+        if !registered {
+            browsers.push(browser);
+        }
+    }
+
+    // 程序可能在缓存之后被删除，每次都重新检查
+    for browser in &mut browsers {
+        browser.broken = platform::check_executable(command_executable(&browser.command));
+    }
+    browsers
+}
+
+// 按配置设置隐藏状态和顺序，返回配置是否因迁移而改变
+fn apply_browser_config(config: &mut Config, browsers: &mut [Browser]) -> bool {
+    let migrated = migrate_config(config, browsers);
+    for browser in browsers.iter_mut() {
+        browser.hidden = config.profile().hidden_browsers.contains(&browser.id);
+    }
+    ordering::apply_manual_order(browsers, &config.browser_order);
+    migrated
+}
+
+fn get_installed_browsers(config: &mut Config) -> Vec<Browser> {
+    let mut browsers = discover_browsers(&config.search_dirs, true);
+    if apply_browser_config(config, &mut browsers) {
+        save_config(config);
+    }
     browsers
 }
 
@@ -180,8 +950,27 @@ enum UrlExtractionState {
     Failed(String),
 }
 
+// 图标网格每行的浏览器数量
+const GRID_COLUMNS: usize = 5;
+
+// 浏览器按钮区域的高度：列表每行50，网格每行70
+fn browser_buttons_height(visible_count: usize, icon_grid: bool) -> f32 {
+    if visible_count == 0 {
+        return 0.0;
+    }
+    let rows_height = if icon_grid {
+        (0..visible_count).step_by(GRID_COLUMNS).count() as f32 * 70.0
+    } else {
+        visible_count as f32 * 50.0
+    };
+    rows_height + 20.0
+}
+
 struct BrowserSelectorApp {
     browsers: Vec<Browser>,
+    config: Config,
+    // 配置文件无法读取的原因，显示在窗口顶部
+    config_error: Option<String>,
     url_to_open: String,
     original_url: String,
     show_settings: bool,
@@ -192,22 +981,46 @@ struct BrowserSelectorApp {
     first_frame: bool,
     url_extraction_state: UrlExtractionState,
     wechat_extraction_handle: Option<std::thread::JoinHandle<Option<String>>>,
+    multi_select: bool,
+    selected_browsers: Vec<String>,
+    new_group_name: String,
+    probe_started: bool,
+    head_probe_handle: Option<std::thread::JoinHandle<Option<HeadInfo>>>,
+    head_info: Option<HeadInfo>,
+    aria2_handle: Option<std::thread::JoinHandle<Result<String, String>>>,
+    // 图标位置 -> 纹理，读取失败的记为 None 避免重复读取
+    icon_textures: HashMap<String, Option<egui::TextureHandle>>,
+    refresh_handle: Option<std::thread::JoinHandle<Vec<Browser>>>,
+    // 上次看到的配置文件状态
+    config_stamp: ConfigStamp,
+    config_watcher: ConfigWatcher,
+    // 导入/导出的文件和选中的部分
+    share_path: String,
+    share_sections: Vec<sharing::Section>,
+    // 等待确认的导入条目
+    pending_import: Option<Vec<sharing::ImportItem>>,
+    team_preset_input: String,
+    new_profile_name: String,
+    // 命令行 --private：以隐私模式打开
+    private: bool,
 }
 
 impl BrowserSelectorApp {
-    fn new(cc: &eframe::CreationContext<'_>, url_to_open: String, browsers: Vec<Browser>) -> Self {
+    fn new(
+        cc: &eframe::CreationContext<'_>,
+        url_to_open: String,
+        browsers: Vec<Browser>,
+        config: Config,
+        config_error: Option<String>,
+    ) -> Self {
         let mut fonts = egui::FontDefinitions::default();
 
-        if let Ok(font_data) = std::fs::read("C:\\Windows\\Fonts\\msyh.ttc") {
-            fonts
-                .font_data
-                .insert("my_font".to_owned(), egui::FontData::from_owned(font_data));
-            fonts
-                .families
-                .entry(egui::FontFamily::Proportional)
-                .or_default()
-                .insert(0, "my_font".to_owned());
-        } else if let Ok(font_data) = std::fs::read("C:\\Windows\\Fonts\\simhei.ttf") {
+        // 加载系统中第一个可用的中文字体
+        if let Some(font_data) = platform::current()
+            .font_paths()
+            .iter()
+            .find_map(|path| std::fs::read(path).ok())
+        {
             fonts
                 .font_data
                 .insert("my_font".to_owned(), egui::FontData::from_owned(font_data));
@@ -224,7 +1037,14 @@ impl BrowserSelectorApp {
         let original_url = url_to_open.clone();
         let (extracted_url, needs_async) = extract_real_url_sync(&url_to_open);
         
-        let url_extraction_state = if needs_async {
+        let team_preset_input = team_preset_input(&config);
+        let config_files = ConfigFiles::of(&config);
+        let config_stamp = config_files.stamp();
+        let config_watcher = ConfigWatcher::start(cc.egui_ctx.clone(), config_files);
+
+        let url_extraction_state = if needs_async && !config.network_extraction_allowed() {
+            UrlExtractionState::Failed("已禁用联网提取".to_string())
+        } else if needs_async {
             UrlExtractionState::Pending
         } else {
             UrlExtractionState::Success(extracted_url.clone())
@@ -232,6 +1052,11 @@ impl BrowserSelectorApp {
 
         Self {
             browsers,
+            config_error: config_error
+                .or_else(|| config.preset_error.clone())
+                .or_else(|| config.policy_error.clone())
+                .or_else(|| config.pattern_error()),
+            config,
             url_to_open: extracted_url,
             original_url,
             show_settings: false,
@@ -242,64 +1067,399 @@ impl BrowserSelectorApp {
             first_frame: true,
             url_extraction_state,
             wechat_extraction_handle: None,
+            multi_select: false,
+            selected_browsers: Vec::new(),
+            new_group_name: String::new(),
+            probe_started: false,
+            head_probe_handle: None,
+            head_info: None,
+            aria2_handle: None,
+            refresh_handle: None,
+            config_stamp,
+            config_watcher,
+            share_path: paths::config_dir().join("fuckHttp-export.json").display().to_string(),
+            share_sections: sharing::SECTIONS.to_vec(),
+            pending_import: None,
+            team_preset_input,
+            new_profile_name: String::new(),
+            private: false,
+            icon_textures: HashMap::new(),
+        }
+    }
+
+    // 读取可见浏览器中还没有缓存的图标
+    fn load_icons(&mut self, ctx: &egui::Context) {
+        for browser in self.browsers.iter().filter(|b| self.config.shows(b)) {
+            let Some(location) = &browser.icon else {
+                continue;
+            };
+            if self.icon_textures.contains_key(location) {
+                continue;
+            }
+            let texture = platform::current().load_icon(location).map(|image| {
+                ctx.load_texture(format!("icon:{}", location), image, egui::TextureOptions::LINEAR)
+            });
+            self.icon_textures.insert(location.clone(), texture);
+        }
+    }
+
+    fn browser_icon(&self, browser: &Browser, size: f32) -> Option<egui::Image<'static>> {
+        let texture = self.icon_textures.get(browser.icon.as_ref()?)?.as_ref()?;
+        Some(egui::Image::from_texture(egui::load::SizedTexture::new(
+            texture.id(),
+            egui::vec2(size, size),
+        )))
+    }
+
+    // 扩展名或域名无法判断是否为文件或媒体时，才需要HEAD请求
+    fn needs_head_probe(&self) -> bool {
+        let aria2_probe = match &self.config.aria2 {
+            Some(aria2) => aria2.probe && !aria2::has_download_extension(&self.url_to_open),
+            None => false,
+        };
+        let media_probe = match &self.config.media {
+            Some(media) => media.probe && !media.matches_url(&self.url_to_open),
+            None => false,
+        };
+        (aria2_probe || media_probe) && self.config.network_extraction_allowed()
+    }
+
+    // 媒体链接：在选择窗口顶部提供播放器
+    fn offer_media_player(&self) -> bool {
+        match &self.config.media {
+            Some(media) => media.is_media(&self.url_to_open, self.head_info.as_ref()),
+            None => false,
         }
     }
-}
 
-fn register_app() -> std::io::Result<()> {
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let path = "Software\\Classes\\fuckHttp";
-    let (key, _) = hkcu.create_subkey(&path)?;
+    fn offer_aria2(&self) -> bool {
+        self.config.aria2.is_some() && aria2::is_download(&self.url_to_open, self.head_info.as_ref())
+    }
 
-    key.set_value("", &"URL:fuckHttp Protocol")?;
-    key.set_value("URL Protocol", &"")?;
+    fn group_members(&self, group: &BrowserGroup) -> Vec<&Browser> {
+        self.config.group_members(group, &self.browsers)
+    }
 
-    let (icon_key, _) = key.create_subkey("DefaultIcon")?;
-    let exe_path = std::env::current_exe()?;
-    icon_key.set_value("", &format!("\"{}\",0", exe_path.to_str().unwrap()))?;
+    // 分组成员的显示名称，未安装的成员显示ID
+    fn group_member_names(&self, group: &BrowserGroup) -> String {
+        group
+            .browsers
+            .iter()
+            .map(|id| match self.browsers.iter().find(|b| &b.id == id) {
+                Some(browser) => self.config.display_name(browser),
+                None => id.as_str(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    // 换用新的配置，浏览器的隐藏状态和顺序随之更新
+    fn replace_config(&mut self, mut config: Config) {
+        // 命令行指定的方案在重新加载后仍然有效
+        config.profile_override = self.config.profile_override.take();
+        let search_dirs_changed = config.search_dirs != self.config.search_dirs;
+        self.config = config;
+        if apply_browser_config(&mut self.config, &mut self.browsers) {
+            save_config(&self.config);
+        }
+        self.selected_browsers.retain(|id| self.browsers.iter().any(|b| &b.id == id));
+        self.team_preset_input = team_preset_input(&self.config);
+        // 团队预设可能换了文件，本窗口的改动也不需要再重新加载
+        let config_files = ConfigFiles::of(&self.config);
+        self.config_stamp = config_files.stamp();
+        self.config_watcher.watch(config_files);
+        if search_dirs_changed && self.refresh_handle.is_none() {
+            let search_dirs = self.config.search_dirs.clone();
+            self.refresh_handle = Some(std::thread::spawn(move || discover_browsers(&search_dirs, true)));
+        }
+    }
 
-    let (command_key, _) = key.create_subkey("shell\\open\\command")?;
-    command_key.set_value(
-        "",
-        &format!("\"{}\" \"%1\"", exe_path.to_str().unwrap()),
-    )?;
+    fn export_config(&mut self) {
+        let exported = sharing::export(&config_value(&self.config), &self.share_sections, self.config.profile_name());
+        let path = PathBuf::from(self.share_path.trim());
+        let result = serde_json::to_string_pretty(&exported)
+            .map_err(|e| e.to_string())
+            .and_then(|content| storage::write_atomic(&path, content.as_bytes()).map_err(|e| e.to_string()));
+        let message = match result {
+            Ok(_) => format!("已导出到 {}", path.display()),
+            Err(e) => format!("导出失败: {}", e),
+        };
+        self.toast_message = Some((message, std::time::Instant::now()));
+    }
 
-    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
-    let capabilities_path = "Software\\fuckHttp\\Capabilities";
-    let (capabilities_key, _) = hklm.create_subkey(capabilities_path)?;
-    capabilities_key.set_value("ApplicationName", &"fuckHttp")?;
-    capabilities_key.set_value(
-        "ApplicationIcon",
-        &format!("\"{}\",0", exe_path.to_str().unwrap()),
-    )?;
-    capabilities_key.set_value("ApplicationDescription", &"A custom browser selector.")?;
+    // 读取导入文件，列出新条目和冲突等待确认
+    fn prepare_import(&mut self) {
+        let message = match read_config_layer(Path::new(self.share_path.trim())) {
+            Ok(imported) => {
+                let plan = sharing::plan_import(
+                    &config_value(&self.config),
+                    &imported,
+                    &self.share_sections,
+                    self.config.profile_name(),
+                );
+                if plan.is_empty() {
+                    Some("没有需要导入的内容".to_string())
+                } else {
+                    self.pending_import = Some(plan);
+                    None
+                }
+            }
+            Err(e) => Some(format!("无法读取导入文件: {}", e)),
+        };
+        if let Some(message) = message {
+            self.toast_message = Some((message, std::time::Instant::now()));
+        }
+    }
 
-    let (url_assoc_key, _) = capabilities_key.create_subkey("URLAssociations")?;
-    url_assoc_key.set_value("http", &"fuckHttp")?;
-    url_assoc_key.set_value("https", &"fuckHttp")?;
+    fn apply_pending_import(&mut self) {
+        let Some(plan) = self.pending_import.take() else {
+            return;
+        };
+        let mut value = config_value(&self.config);
+        sharing::apply_import(&mut value, &plan, self.config.profile_name());
+        let message = match serde_json::from_value::<Config>(value) {
+            Ok(config) => {
+                let config = apply_team_preset(config);
+                save_config(&config);
+                self.replace_config(config);
+                format!("已导入 {} 项", plan.iter().filter(|item| item.take_theirs).count())
+            }
+            Err(e) => format!("导入失败: {}", e),
+        };
+        self.toast_message = Some((message, std::time::Instant::now()));
+    }
+
+    fn set_team_preset(&mut self) {
+        let Ok(mut config) = serde_json::from_value::<Config>(config_value(&self.config)) else {
+            return;
+        };
+        let path = self.team_preset_input.trim();
+        config.team_preset = (!path.is_empty()).then(|| PathBuf::from(path));
+        let config = apply_team_preset(config);
+        save_config(&config);
+        self.replace_config(config);
+    }
+
+    // 设置中的导入/导出和团队预设
+    fn sharing_settings(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("导入/导出");
+        ui.horizontal_wrapped(|ui| {
+            for section in sharing::SECTIONS {
+                let mut selected = self.share_sections.contains(section);
+                if ui.checkbox(&mut selected, section.label()).changed() {
+                    if selected {
+                        self.share_sections.push(*section);
+                    } else {
+                        self.share_sections.retain(|s| s != section);
+                    }
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("文件:");
+            ui.add(egui::TextEdit::singleline(&mut self.share_path).desired_width(220.0));
+        });
+        ui.horizontal(|ui| {
+            let has_path = !self.share_path.trim().is_empty() && !self.share_sections.is_empty();
+            if ui.add_enabled(has_path, egui::Button::new("导出")).clicked() {
+                self.export_config();
+            }
+            if ui.add_enabled(has_path, egui::Button::new("导入")).clicked() {
+                self.prepare_import();
+            }
+        });
+
+        let mut finished = None;
+        if let Some(plan) = &mut self.pending_import {
+            ui.label(egui::RichText::new("选择要导入的条目，冲突的条目可以保留我的或使用导入的:").size(12.0));
+            for item in plan.iter_mut() {
+                ui.horizontal(|ui| {
+                    let label = format!("{}: {}", item.section.label(), item.key);
+                    let theirs = serde_json::to_string(&item.theirs).unwrap_or_default();
+                    match &item.mine {
+                        Some(mine) => {
+                            let mine = serde_json::to_string(mine).unwrap_or_default();
+                            ui.add(egui::Label::new(label).truncate(true))
+                                .on_hover_text(format!("我的: {}\n导入的: {}", mine, theirs));
+                            ui.radio_value(&mut item.take_theirs, false, "保留我的");
+                            ui.radio_value(&mut item.take_theirs, true, "使用导入的");
+                        }
+                        None => {
+                            ui.checkbox(&mut item.take_theirs, "");
+                            ui.add(egui::Label::new(label).truncate(true)).on_hover_text(theirs);
+                        }
+                    }
+                });
+            }
+            ui.horizontal(|ui| {
+                if ui.button("应用导入").clicked() {
+                    finished = Some(true);
+                }
+                if ui.button("取消").clicked() {
+                    finished = Some(false);
+                }
+            });
+        }
+        match finished {
+            Some(true) => self.apply_pending_import(),
+            Some(false) => self.pending_import = None,
+            None => {}
+        }
+
+        ui.separator();
+        ui.heading("团队预设");
+        ui.label(
+            egui::RichText::new("预设中的规则、动作、分组等排在自己的配置之后，同名条目以自己的为准；预设文件只读")
+                .size(12.0)
+                .color(egui::Color32::GRAY),
+        );
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.team_preset_input)
+                    .hint_text("预设文件路径，例如共享盘上的 team.json")
+                    .desired_width(260.0),
+            );
+            if ui.button("应用").clicked() {
+                self.set_team_preset();
+            }
+        });
+        if let Some(error) = &self.config.preset_error {
+            ui.label(egui::RichText::new(format!("⚠ {}", error)).size(12.0).color(egui::Color32::from_rgb(255, 0, 0)));
+        }
+    }
+
+    // 切换配置方案，None 为默认方案；会取消命令行指定的方案
+    fn switch_profile(&mut self, name: Option<String>) {
+        self.config.active_profile = name;
+        self.config.profile_override = None;
+        save_config(&self.config);
+        apply_browser_config(&mut self.config, &mut self.browsers);
+        let message = format!("已切换到方案: {}", self.config.profile_name().unwrap_or("默认"));
+        self.toast_message = Some((message, std::time::Instant::now()));
+    }
+
+    // 设置中的配置方案和当前方案的默认浏览器
+    fn profile_settings(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("配置方案");
+        ui.label(
+            egui::RichText::new("每个方案有自己的隐藏浏览器、路由规则、默认浏览器和联网提取设置")
+                .size(12.0)
+                .color(egui::Color32::GRAY),
+        );
+        let current = self.config.profile_name().map(str::to_string);
+        let mut removed = None;
+        for name in self.config.profiles.keys() {
+            ui.horizontal(|ui| {
+                let label = if current.as_ref() == Some(name) {
+                    format!("{}（当前）", name)
+                } else {
+                    name.clone()
+                };
+                ui.label(label);
+                if ui.button("删除").clicked() {
+                    removed = Some(name.clone());
+                }
+            });
+        }
+        if let Some(name) = removed {
+            self.config.profiles.remove(&name);
+            if current.as_ref() == Some(&name) {
+                self.switch_profile(None);
+            } else {
+                save_config(&self.config);
+            }
+        }
 
-    let registered_apps_path = "Software\\RegisteredApplications";
-    let registered_apps_key = hklm.open_subkey_with_flags(registered_apps_path, KEY_WRITE)?;
-    registered_apps_key.set_value(
-        "fuckHttp",
-        &"Software\\fuckHttp\\Capabilities".to_string(),
-    )?;
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_profile_name)
+                    .hint_text("方案名称，例如 工作")
+                    .desired_width(160.0),
+            );
+            let name = self.new_profile_name.trim().to_string();
+            let can_create = !name.is_empty() && name != "默认" && !self.config.profiles.contains_key(&name);
+            if ui
+                .add_enabled(can_create, egui::Button::new("新建"))
+                .on_hover_text("复制当前方案的设置")
+                .clicked()
+            {
+                let settings = self.config.profile().clone();
+                self.config.profiles.insert(name.clone(), settings);
+                self.new_profile_name.clear();
+                self.switch_profile(Some(name));
+            }
+        });
 
-    Ok(())
-}
+        ui.horizontal(|ui| {
+            ui.label("默认浏览器:");
+            let mut default_browser = self.config.profile().default_browser.clone();
+            let selected_text = match self.browsers.iter().find(|b| Some(&b.id) == default_browser.as_ref()) {
+                Some(browser) => self.config.display_name(browser).to_string(),
+                None => "无".to_string(),
+            };
+            egui::ComboBox::from_id_source("default_browser")
+                .selected_text(selected_text)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut default_browser, None, "无");
+                    for browser in self.browsers.iter().filter(|b| !self.config.policy.blocks(b)) {
+                        let name = self.config.display_name(browser);
+                        ui.selectable_value(&mut default_browser, Some(browser.id.clone()), name);
+                    }
+                });
+            if default_browser != self.config.profile().default_browser {
+                self.config.profile_mut().default_browser = default_browser;
+                save_config(&self.config);
+            }
+        });
+    }
 
-fn unregister_app() -> std::io::Result<()> {
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    hkcu.delete_subkey_all("Software\\Classes\\fuckHttp")?;
+    // 配置文件被同步工具或手工修改后重新加载
+    fn check_config_changes(&mut self) {
+        if !self.config_watcher.take_changed() {
+            return;
+        }
+        let stamp = ConfigFiles::of(&self.config).stamp();
+        let Some(layers_changed) = reload_needed(&self.config_stamp, &stamp) else {
+            return;
+        };
+        self.config_stamp = stamp;
 
-    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
-    hklm.delete_subkey_all("Software\\fuckHttp")?;
+        match reload_config() {
+            // 本窗口自己保存的内容，团队预设和管理员策略文件也没有变化
+            Ok(config) if !layers_changed && config_value(&config) == config_value(&self.config) => {
+                self.config_error = self.config.pattern_error();
+            }
+            Ok(config) => {
+                self.replace_config(config);
+                self.config_error = self.config.pattern_error();
+                self.toast_message = Some(("配置已重新加载".to_string(), std::time::Instant::now()));
+            }
+            Err(e) => {
+                self.config_error = Some(format!("配置文件有误，未重新加载: {}", e));
+                self.toast_message = Some(("配置文件有误，继续使用原来的配置".to_string(), std::time::Instant::now()));
+            }
+        }
+    }
 
-    let registered_apps_key =
-        hklm.open_subkey_with_flags("Software\\RegisteredApplications", KEY_WRITE)?;
-    registered_apps_key.delete_value("fuckHttp")?;
+    // 记录使用次数，用于按使用频率或最近使用排序
+    fn record_usage(&mut self, ids: &[String]) {
+        let now = ordering::now();
+        for id in ids {
+            ordering::record(&mut self.config.usage, id, now);
+        }
+        save_config(&self.config);
+    }
 
-    Ok(())
+    // 在多个浏览器中打开同一链接
+    fn launch_all(&self, browsers: &[&Browser]) {
+        for browser in browsers {
+            if let Err(e) = launch_browser(browser, &self.url_to_open, self.private) {
+                eprintln!("启动浏览器失败 ({}): {}", browser.name, e);
+            }
+        }
+    }
 }
 
 impl eframe::App for BrowserSelectorApp {
@@ -322,6 +1482,9 @@ impl eframe::App for BrowserSelectorApp {
                     if handle.is_finished() {
                         match handle.join() {
                             Ok(Some(real_url)) => {
+                                if apply_routing(&self.config, &self.browsers, &real_url, self.private) {
+                                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                                }
                                 self.url_to_open = real_url.clone();
                                 self.url_extraction_state = UrlExtractionState::Success(real_url);
                             }
@@ -342,7 +1505,67 @@ impl eframe::App for BrowserSelectorApp {
             }
             _ => {}
         }
+
+        // 链接确定后在后台检查响应头
+        if !self.probe_started && matches!(self.url_extraction_state, UrlExtractionState::Success(_)) {
+            self.probe_started = true;
+            if self.needs_head_probe() {
+                let url = self.url_to_open.clone();
+                self.head_probe_handle = Some(std::thread::spawn(move || probe::head(&url)));
+            }
+        }
+        if let Some(handle) = self.head_probe_handle.take() {
+            if handle.is_finished() {
+                self.head_info = handle.join().ok().flatten();
+                ctx.request_repaint();
+            } else {
+                self.head_probe_handle = Some(handle);
+                ctx.request_repaint_after(std::time::Duration::from_millis(100));
+            }
+        }
+
+        // 检查 aria2 发送结果
+        if let Some(handle) = self.aria2_handle.take() {
+            if handle.is_finished() {
+                let message = match handle.join() {
+                    Ok(Ok(_)) => "已发送到 aria2".to_string(),
+                    Ok(Err(e)) => format!("发送到 aria2 失败: {}", e),
+                    Err(_) => "发送到 aria2 失败".to_string(),
+                };
+                self.toast_message = Some((message, std::time::Instant::now()));
+                ctx.request_repaint();
+            } else {
+                self.aria2_handle = Some(handle);
+                ctx.request_repaint_after(std::time::Duration::from_millis(100));
+            }
+        }
+
+        // 检查浏览器列表刷新结果
+        if let Some(handle) = self.refresh_handle.take() {
+            if handle.is_finished() {
+                let message = match handle.join() {
+                    Ok(mut browsers) => {
+                        if apply_browser_config(&mut self.config, &mut browsers) {
+                            save_config(&self.config);
+                        }
+                        self.selected_browsers.retain(|id| browsers.iter().any(|b| &b.id == id));
+                        self.browsers = browsers;
+                        format!("已刷新，共 {} 个浏览器", self.browsers.len())
+                    }
+                    Err(_) => "刷新浏览器列表失败".to_string(),
+                };
+                self.toast_message = Some((message, std::time::Instant::now()));
+                ctx.request_repaint();
+            } else {
+                self.refresh_handle = Some(handle);
+                ctx.request_repaint_after(std::time::Duration::from_millis(100));
+            }
+        }
+
+        self.check_config_changes();
         
+        self.load_icons(ctx);
+
         // 计算当前窗口高度（根据实际浏览器数量）
         let mut window_height = 20.0; // 基础边距
         
@@ -359,6 +1582,10 @@ impl eframe::App for BrowserSelectorApp {
             }
         }
         
+        if self.config_error.is_some() {
+            window_height += 20.0;
+        }
+
         // URL滚动框：固定高度
         window_height += 60.0;
         
@@ -366,10 +1593,40 @@ impl eframe::App for BrowserSelectorApp {
         window_height += 20.0;
         
         // 浏览器选项高度（根据实际可见浏览器数量）
-        let visible_browsers_count = self.browsers.iter().filter(|b| !b.hidden).count();
-        if visible_browsers_count > 0 {
-            window_height += visible_browsers_count as f32 * 50.0;
-            window_height += 20.0;
+        let visible_browsers_count = self.browsers.iter().filter(|b| self.config.shows(b)).count();
+        window_height += browser_buttons_height(
+            visible_browsers_count,
+            self.config.icon_grid && !self.multi_select,
+        );
+
+        // 媒体播放和应用内打开按钮
+        if !self.multi_select && self.offer_media_player() {
+            window_height += 50.0;
+        }
+        if !self.multi_select && deeplink::rewrite(&self.config.deep_links, &self.url_to_open).is_some() {
+            window_height += 50.0;
+        }
+
+        // 多选模式下的“在所有选中的浏览器中打开”和保存分组两行，否则为分组按钮
+        if self.multi_select {
+            window_height += 90.0;
+        } else {
+            let groups_count = self
+                .config
+                .browser_groups
+                .iter()
+                .filter(|g| !self.group_members(g).is_empty())
+                .count();
+            window_height += groups_count as f32 * 50.0;
+
+            // 动作和 aria2 按钮（次要按钮，高度较小）
+            let mut secondary_count = actions::matching_actions(&self.config.actions, &self.url_to_open).len();
+            if self.offer_aria2() {
+                secondary_count += 1;
+            }
+            if secondary_count > 0 {
+                window_height += 10.0 + secondary_count as f32 * 33.0;
+            }
         }
         
         // 底部边距
@@ -412,6 +1669,9 @@ impl eframe::App for BrowserSelectorApp {
         egui::TopBottomPanel::top("title_bar").show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.heading("fuckHttp");
+                if self.private {
+                    ui.label(egui::RichText::new("隐私模式").size(12.0).color(egui::Color32::GRAY));
+                }
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                     ui.style_mut().spacing.button_padding = egui::vec2(4.0, 2.0);
                     if ui.add(egui::Button::new("❌").small()).clicked() {
@@ -421,6 +1681,33 @@ impl eframe::App for BrowserSelectorApp {
                         self.show_settings = !self.show_settings;
                         self.message = None; // Clear message when toggling settings
                     }
+                    // 配置方案快速切换
+                    if !self.config.profiles.is_empty() {
+                        let current = self.config.profile_name().map(str::to_string);
+                        let mut selected = current.clone();
+                        egui::ComboBox::from_id_source("profile_switcher")
+                            .selected_text(current.as_deref().unwrap_or("默认"))
+                            .width(80.0)
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut selected, None, "默认");
+                                for name in self.config.profiles.keys() {
+                                    ui.selectable_value(&mut selected, Some(name.clone()), name);
+                                }
+                            })
+                            .response
+                            .on_hover_text("配置方案");
+                        if selected != current {
+                            self.switch_profile(selected);
+                        }
+                    }
+                    if ui
+                        .add(egui::Button::new("☑").small().selected(self.multi_select))
+                        .on_hover_text("多选模式")
+                        .clicked()
+                    {
+                        self.multi_select = !self.multi_select;
+                        self.selected_browsers.clear();
+                    }
                     if ui.add(egui::Button::new("📋").small()).on_hover_text("复制链接").clicked() {
                         ui.output_mut(|o| o.copied_text = self.url_to_open.clone());
                         self.toast_message = Some(("链接已复制到剪贴板".to_string(), std::time::Instant::now()));
@@ -431,82 +1718,211 @@ impl eframe::App for BrowserSelectorApp {
 
         if self.show_settings {
             egui::CentralPanel::default().show(ctx, |ui| {
-                ui.heading("设置");
-                ui.add_space(10.0);
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.heading("设置");
+                    let config_dir = paths::config_dir();
+                    let location = if paths::portable_data_dir().is_some() {
+                        format!("便携模式，数据保存在 {}", config_dir.display())
+                    } else {
+                        format!("配置保存在 {}", config_dir.display())
+                    };
+                    ui.label(egui::RichText::new(location).size(12.0).color(egui::Color32::GRAY));
+                    if let Some(error) = &self.config_error {
+                        ui.label(egui::RichText::new(format!("⚠ {}", error)).size(12.0).color(egui::Color32::from_rgb(255, 0, 0)));
+                    }
+                    ui.add_space(10.0);
+
+                    self.profile_settings(ui);
+
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.heading("浏览器列表");
+                        let refreshing = self.refresh_handle.is_some();
+                        let text = if refreshing { "正在刷新..." } else { "🔄 刷新" };
+                        if ui
+                            .add_enabled(!refreshing, egui::Button::new(text))
+                            .on_hover_text("重新查找已安装的浏览器")
+                            .clicked()
+                        {
+                            let search_dirs = self.config.search_dirs.clone();
+                            self.refresh_handle =
+                                Some(std::thread::spawn(move || discover_browsers(&search_dirs, false)));
+                        }
+                    });
+                    let mut config_changed = false;
+                    // 拖动 ☰ 调整顺序，别名为空时显示原名称
+                    let mut moved = None;
+                    let aliases = &mut self.config.aliases;
+                    let policy = &self.config.policy;
+                    for (index, browser) in self.browsers.iter_mut().enumerate() {
+                        let row = ui.horizontal(|ui| {
+                            ui.dnd_drag_source(egui::Id::new(("browser_order", index)), index, |ui| {
+                                ui.label("☰");
+                            });
+                            let mut alias = aliases.get(&browser.id).cloned().unwrap_or_default();
+                            let alias_edit = egui::TextEdit::singleline(&mut alias)
+                                .hint_text(&browser.name)
+                                .desired_width(180.0);
+                            if ui.add(alias_edit).on_hover_text(&browser.name).changed() {
+                                if alias.trim().is_empty() {
+                                    aliases.remove(&browser.id);
+                                } else {
+                                    aliases.insert(browser.id.clone(), alias);
+                                }
+                                config_changed = true;
+                            }
+                            if policy.blocks(browser) {
+                                ui.add_enabled(false, egui::Button::new("🔒 已禁用"))
+                                    .on_disabled_hover_text("已被管理员策略禁用");
+                            } else {
+                                let button_text = if browser.hidden { "显示" } else { "隐藏" };
+                                if ui.button(button_text).clicked() {
+                                    browser.hidden = !browser.hidden;
+                                    config_changed = true;
+                                }
+                            }
+                        });
+                        if row.response.dnd_hover_payload::<usize>().is_some() {
+                            // 放下位置的提示线
+                            let rect = row.response.rect;
+                            ui.painter().hline(rect.x_range(), rect.top(), ui.visuals().selection.stroke);
+                        }
+                        if let Some(from) = row.response.dnd_release_payload::<usize>() {
+                            moved = Some((*from, index));
+                        }
+                        if let Some(reason) = &browser.broken {
+                            ui.label(egui::RichText::new(format!("⚠ {}", reason)).color(egui::Color32::from_rgb(255, 165, 0)).size(12.0));
+                        }
+                    }
+                    if let Some((from, to)) = moved {
+                        if from != to && from < self.browsers.len() {
+                            let browser = self.browsers.remove(from);
+                            self.browsers.insert(to.min(self.browsers.len()), browser);
+                            self.config.browser_order = self.browsers.iter().map(|b| b.id.clone()).collect();
+                            config_changed = true;
+                        }
+                    }
 
-                ui.separator();
-                ui.heading("浏览器列表");
-                let mut config_changed = false;
-                for browser in &mut self.browsers {
                     ui.horizontal(|ui| {
-                        ui.label(&browser.name);
-                        let button_text = if browser.hidden { "显示" } else { "隐藏" };
-                        if ui.button(button_text).clicked() {
-                            browser.hidden = !browser.hidden;
+                        ui.label("排序:");
+                        for (mode, label) in [
+                            (SortMode::Manual, "手动"),
+                            (SortMode::Frequency, "使用频率"),
+                            (SortMode::Recency, "最近使用"),
+                        ] {
+                            if ui.radio_value(&mut self.config.sort_mode, mode, label).changed() {
+                                config_changed = true;
+                            }
+                        }
+                    });
+                    if ui.checkbox(&mut self.config.icon_grid, "图标网格布局").changed() {
+                        config_changed = true;
+                    }
+                    if ui.checkbox(&mut self.config.auto_hide_broken, "自动隐藏无效的浏览器").changed() {
+                        config_changed = true;
+                    }
+                    ui.horizontal(|ui| {
+                        let locked = self.config.policy.disable_network_extraction.is_some();
+                        let mut allowed = self.config.network_extraction_allowed();
+                        let checkbox = egui::Checkbox::new(&mut allowed, "联网提取链接（微信页面、HEAD 检查）");
+                        if ui.add_enabled(!locked, checkbox).changed() {
+                            self.config.profile_mut().disable_network_extraction = !allowed;
                             config_changed = true;
                         }
+                        if locked {
+                            ui.label("🔒").on_hover_text("由管理员策略设定");
+                        }
                     });
-                }
 
-                if config_changed {
-                    let hidden_browsers = self
-                        .browsers
-                        .iter()
-                        .filter(|b| b.hidden)
-                        .map(|b| b.name.clone())
-                        .collect();
-                    save_config(&Config { hidden_browsers });
-                }
+                    if !self.config.policy.is_empty() || self.config.policy_error.is_some() {
+                        ui.separator();
+                        ui.heading("🔒 管理员策略");
+                        let policy = &self.config.policy;
+                        let gray = |text: String| egui::RichText::new(text).size(12.0).color(egui::Color32::GRAY);
+                        if !policy.rules.is_empty() {
+                            ui.label(gray(format!("强制的路由规则: {} 条，优先于自己的规则", policy.rules.len())));
+                        }
+                        if !policy.blocked_browsers.is_empty() {
+                            ui.label(gray(format!("禁止使用的浏览器: {}", policy.blocked_browsers.join(", "))));
+                        }
+                        if let Some(error) = &self.config.policy_error {
+                            ui.label(egui::RichText::new(format!("⚠ {}", error)).size(12.0).color(egui::Color32::from_rgb(255, 0, 0)));
+                        }
+                    }
+
+                    if !self.config.browser_groups.is_empty() {
+                        ui.separator();
+                        ui.heading("浏览器分组");
+                        let mut removed_group = None;
+                        for (index, group) in self.config.browser_groups.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                ui.label(format!("{} ({})", group.name, self.group_member_names(group)));
+                                if ui.button("删除").clicked() {
+                                    removed_group = Some(index);
+                                }
+                            });
+                        }
+                        if let Some(index) = removed_group {
+                            self.config.browser_groups.remove(index);
+                            config_changed = true;
+                        }
+                    }
+
+                    if config_changed {
+                        // 保留当前未安装的浏览器的隐藏状态
+                        let browsers = &self.browsers;
+                        let hidden_browsers = &mut self.config.profile_mut().hidden_browsers;
+                        hidden_browsers.retain(|id| !browsers.iter().any(|b| &b.id == id));
+                        hidden_browsers.extend(browsers.iter().filter(|b| b.hidden).map(|b| b.id.clone()));
+                        save_config(&self.config);
+                    }
+
+                    self.sharing_settings(ui);
 
-                ui.separator();
+                    ui.separator();
 
-                ui.add_space(10.0);
+                    ui.add_space(10.0);
 
-                if ui.button("注册到系统").clicked() {
-                    if !is_elevated() {
-                        let exe = std::env::current_exe().unwrap();
-                        match runas::Command::new(exe).arg("--register").status() {
-                            Ok(status) if status.success() => {
+                    if ui.button("注册到系统").clicked() {
+                        let platform = platform::current();
+                        if !platform.is_elevated() {
+                            if platform.run_elevated("register") {
                                 self.message = Some("注册成功!".to_string());
-                            }
-                            _ => {
+                            } else {
                                 self.message = Some("注册失败 (需要管理员权限).".to_string());
                             }
-                        }
-                    } else {
-                        match register_app() {
-                            Ok(_) => self.message = Some("注册成功!".to_string()),
-                            Err(e) => {
-                                self.message = Some(format!("注册失败: {}", e));
+                        } else {
+                            match platform.register() {
+                                Ok(_) => self.message = Some("注册成功!".to_string()),
+                                Err(e) => {
+                                    self.message = Some(format!("注册失败: {}", e));
+                                }
                             }
                         }
                     }
-                }
-                ui.add_space(5.0);
-                if ui.button("从系统卸载").clicked() {
-                    if !is_elevated() {
-                        let exe = std::env::current_exe().unwrap();
-                        match runas::Command::new(exe).arg("--unregister").status() {
-                            Ok(status) if status.success() => {
+                    ui.add_space(5.0);
+                    if ui.button("从系统卸载").clicked() {
+                        let platform = platform::current();
+                        if !platform.is_elevated() {
+                            if platform.run_elevated("unregister") {
                                 self.message = Some("卸载成功!".to_string());
-                            }
-                            _ => {
+                            } else {
                                 self.message = Some("卸载失败 (需要管理员权限).".to_string());
                             }
-                        }
-                    } else {
-                        match unregister_app() {
-                            Ok(_) => self.message = Some("卸载成功!".to_string()),
-                            Err(e) => {
-                                self.message = Some(format!("卸载失败: {}", e));
+                        } else {
+                            match platform.unregister() {
+                                Ok(_) => self.message = Some("卸载成功!".to_string()),
+                                Err(e) => {
+                                    self.message = Some(format!("卸载失败: {}", e));
+                                }
                             }
                         }
                     }
-                }
-                if let Some(msg) = &self.message {
-                    ui.add_space(10.0);
-                    ui.label(msg);
-                }
+                    if let Some(msg) = &self.message {
+                        ui.add_space(10.0);
+                        ui.label(msg);
+                    }
+                });
             });
         } else {
             egui::CentralPanel::default()
@@ -517,6 +1933,12 @@ impl eframe::App for BrowserSelectorApp {
                  )
                  .show(ctx, |ui| {
                     ui.add_space(4.0);
+
+                    if let Some(error) = &self.config_error {
+                        ui.add(egui::Label::new(egui::RichText::new(format!("⚠ {}", error)).color(egui::Color32::from_rgb(255, 0, 0)).size(12.0)).truncate(true))
+                            .on_hover_text(error);
+                        ui.add_space(2.0);
+                    }
                     
                     // 显示URL提取状态
                     match &self.url_extraction_state {
@@ -526,17 +1948,15 @@ impl eframe::App for BrowserSelectorApp {
                             });
                             ui.add_space(2.0);
                         }
-                        UrlExtractionState::Success(_) => {
-                            if self.original_url != self.url_to_open {
-                                ui.horizontal(|ui| {
-                                    ui.add(egui::Label::new(egui::RichText::new("🔓 已提取真实链接").color(egui::Color32::from_rgb(0, 150, 0)).size(12.0)));
-                                });
-                                ui.add_space(2.0);
-                            }
+                        UrlExtractionState::Success(extracted_url) if &self.original_url != extracted_url => {
+                            ui.horizontal(|ui| {
+                                ui.add(egui::Label::new(egui::RichText::new("🔓 已提取真实链接").color(egui::Color32::from_rgb(0, 150, 0)).size(12.0)));
+                            });
+                            ui.add_space(2.0);
                         }
                         UrlExtractionState::Failed(error) => {
                             ui.horizontal(|ui| {
-                                ui.add(egui::Label::new(egui::RichText::new(&format!("❌ 提取失败: {}", error)).color(egui::Color32::from_rgb(255, 0, 0)).size(12.0)));
+                                ui.add(egui::Label::new(egui::RichText::new(format!("❌ 提取失败: {}", error)).color(egui::Color32::from_rgb(255, 0, 0)).size(12.0)));
                             });
                             ui.add_space(2.0);
                         }
@@ -561,38 +1981,225 @@ impl eframe::App for BrowserSelectorApp {
                     ui.add_space(10.0);
                     let button_width = ui.available_width() - 20.0;
                     ui.vertical_centered(|ui| {
-                        let visible_browsers: Vec<_> = self.browsers.iter().filter(|b| !b.hidden).collect();
-                        for (index, browser) in visible_browsers.iter().enumerate() {
-                            let button = egui::Button::new(&browser.name)
-                                .min_size(egui::vec2(button_width, 40.0));
-                            if ui.add(button).clicked() {
-                                // 更安全的命令解析方式
-                                let command = browser.command.trim();
-                                let executable = if command.starts_with('"') {
-                                    // 处理带引号的路径
-                                    if let Some(end_quote) = command[1..].find('"') {
-                                        &command[1..end_quote + 1]
-                                    } else {
-                                        command
+                        // 媒体链接优先用本地播放器打开
+                        if !self.multi_select && self.offer_media_player() {
+                            if let Some(media) = &self.config.media {
+                                let button = egui::Button::new("▶ 播放")
+                                    .min_size(egui::vec2(button_width, 40.0));
+                                if ui.add(button).on_hover_text(&media.player).clicked() {
+                                    if let Err(e) = actions::spawn_args(&media.player_command(&self.url_to_open)) {
+                                        eprintln!("启动播放器失败: {}", e);
+                                    }
+                                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                                }
+                                ui.add_space(5.0);
+                            }
+                        }
+
+                        // 有对应桌面应用的链接，改写为应用协议后交给系统打开
+                        if !self.multi_select {
+                            if let Some(link) = deeplink::rewrite(&self.config.deep_links, &self.url_to_open) {
+                                let button = egui::Button::new(format!("在应用中打开 ({})", link.app))
+                                    .min_size(egui::vec2(button_width, 40.0));
+                                if ui.add(button).on_hover_text(&link.uri).clicked() {
+                                    if let Err(e) = deeplink::open_uri(&link.uri) {
+                                        eprintln!("在应用中打开失败: {}", e);
+                                    }
+                                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                                }
+                                ui.add_space(5.0);
+                            }
+                        }
+
+                        let mut visible_browsers = ordering::sort_for_selector(
+                            self.browsers.iter().filter(|b| self.config.shows(b)).collect(),
+                            self.config.sort_mode,
+                            &self.config.usage,
+                        );
+                        // 默认浏览器排在最前面，按回车直接打开
+                        let default_browser = self.config.profile().default_browser.clone();
+                        ordering::default_first(&mut visible_browsers, default_browser.as_deref());
+                        let enter_pressed = !self.multi_select && ctx.input(|i| i.key_pressed(egui::Key::Enter));
+                        // 启动失败时保留窗口并提示原因
+                        let mut launch_error = None;
+                        // 本次打开的浏览器，稍后记录使用次数
+                        let mut launched: Vec<String> = Vec::new();
+                        if self.config.icon_grid && !self.multi_select {
+                            // 网格模式：只显示图标，名称作为悬停提示
+                            let cell_width = (button_width - (GRID_COLUMNS - 1) as f32 * 5.0) / GRID_COLUMNS as f32;
+                            egui::Grid::new("browser_grid")
+                                .spacing(egui::vec2(5.0, 5.0))
+                                .show(ui, |ui| {
+                                    for row in visible_browsers.chunks(GRID_COLUMNS) {
+                                        for browser in row {
+                                            let name = self.config.display_name(browser);
+                                            let button = match self.browser_icon(browser, 40.0) {
+                                                Some(icon) => egui::Button::image(icon),
+                                                None => egui::Button::new(egui::RichText::new(name).size(11.0)).wrap(true),
+                                            };
+                                            let is_default = default_browser.as_ref() == Some(&browser.id);
+                                            let mut hover_text = match &browser.broken {
+                                                Some(reason) => format!("{}\n⚠ {}", name, reason),
+                                                None => name.to_string(),
+                                            };
+                                            if is_default {
+                                                hover_text.push_str("\n⭐ 默认浏览器，按回车打开");
+                                            }
+                                            let response = ui.add_sized([cell_width, 64.0], button).on_hover_text(hover_text);
+                                            if response.clicked() || (is_default && enter_pressed) {
+                                                match launch_browser(browser, &self.url_to_open, self.private) {
+                                                    Ok(()) => {
+                                                        launched.push(browser.id.clone());
+                                                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                                                    }
+                                                    Err(e) => launch_error = Some(format!("启动 {} 失败: {}", name, e)),
+                                                }
+                                            }
+                                        }
+                                        ui.end_row();
+                                    }
+                                });
+                        } else {
+                            for (index, browser) in visible_browsers.iter().enumerate() {
+                                let name = self.config.display_name(browser);
+                                if self.multi_select {
+                                    // 多选模式：每行作为复选框切换选中状态
+                                    let selected = self.selected_browsers.contains(&browser.id);
+                                    let label = format!("{} {}", if selected { "☑" } else { "☐" }, name);
+                                    let button = match self.browser_icon(browser, 24.0) {
+                                        Some(icon) => egui::Button::image_and_text(icon, label),
+                                        None => egui::Button::new(label),
+                                    }
+                                    .selected(selected)
+                                    .min_size(egui::vec2(button_width, 40.0));
+                                    if ui.add(button).clicked() {
+                                        if selected {
+                                            self.selected_browsers.retain(|id| id != &browser.id);
+                                        } else {
+                                            self.selected_browsers.push(browser.id.clone());
+                                        }
                                     }
                                 } else {
-                                    // 处理不带引号的路径，取第一个空格前的部分
-                                    command.split_whitespace().next().unwrap_or(command)
-                                };
-
-                                if !executable.is_empty() {
-                                    if let Err(e) = Command::new(executable)
-                                        .arg(&self.url_to_open)
-                                        .spawn() {
-                                        eprintln!("启动浏览器失败: {}", e);
+                                    let is_default = default_browser.as_ref() == Some(&browser.id);
+                                    let label = match &browser.broken {
+                                        Some(_) => format!("⚠ {}", name),
+                                        None if is_default => format!("⭐ {}", name),
+                                        None => name.to_string(),
+                                    };
+                                    let button = match self.browser_icon(browser, 24.0) {
+                                        Some(icon) => egui::Button::image_and_text(icon, label),
+                                        None => egui::Button::new(label),
+                                    }
+                                    .min_size(egui::vec2(button_width, 40.0));
+                                    let mut response = ui.add(button);
+                                    if let Some(reason) = &browser.broken {
+                                        response = response.on_hover_text(reason);
                                     }
+                                    if response.clicked() || (is_default && enter_pressed) {
+                                        match launch_browser(browser, &self.url_to_open, self.private) {
+                                            Ok(()) => {
+                                                launched.push(browser.id.clone());
+                                                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                                            }
+                                            Err(e) => launch_error = Some(format!("启动 {} 失败: {}", name, e)),
+                                        }
+                                    }
+                                }
+                                // 只在不是最后一个按钮时添加间距
+                                if index < visible_browsers.len() - 1 {
+                                    ui.add_space(5.0);
                                 }
+                            }
+                        }
+                        if let Some(message) = launch_error {
+                            self.toast_message = Some((message, std::time::Instant::now()));
+                        }
+
+                        if self.multi_select {
+                            ui.add_space(10.0);
+                            let selected: Vec<&Browser> = visible_browsers
+                                .iter()
+                                .filter(|b| self.selected_browsers.contains(&b.id))
+                                .copied()
+                                .collect();
+                            let open_all = egui::Button::new(format!("在所有选中的浏览器中打开 ({})", selected.len()))
+                                .min_size(egui::vec2(button_width, 40.0));
+                            if ui.add_enabled(!selected.is_empty(), open_all).clicked() {
+                                self.launch_all(&selected);
+                                launched.extend(selected.iter().map(|b| b.id.clone()));
                                 ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                             }
-                            // 只在不是最后一个按钮时添加间距
-                            if index < visible_browsers.len() - 1 {
+                            ui.add_space(5.0);
+                            ui.horizontal(|ui| {
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut self.new_group_name)
+                                        .hint_text("分组名称")
+                                        .desired_width(button_width - 90.0),
+                                );
+                                let can_save = !selected.is_empty() && !self.new_group_name.trim().is_empty();
+                                if ui.add_enabled(can_save, egui::Button::new("保存为分组")).clicked() {
+                                    self.config.save_group(&self.new_group_name, &selected);
+                                    save_config(&self.config);
+                                    self.toast_message = Some(("分组已保存".to_string(), std::time::Instant::now()));
+                                    self.new_group_name.clear();
+                                }
+                            });
+                        } else {
+                            // 已保存的浏览器分组：一键在所有成员中打开
+                            for group in &self.config.browser_groups {
+                                let members = self.group_members(group);
+                                if members.is_empty() {
+                                    continue;
+                                }
                                 ui.add_space(5.0);
+                                let button = egui::Button::new(format!("🗂 {}", group.name))
+                                    .min_size(egui::vec2(button_width, 40.0));
+                                if ui.add(button).on_hover_text(self.group_member_names(group)).clicked() {
+                                    self.launch_all(&members);
+                                    launched.extend(members.iter().map(|b| b.id.clone()));
+                                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                                }
                             }
+
+                            // 非浏览器动作：下载器、播放器、脚本等
+                            let matching = actions::matching_actions(&self.config.actions, &self.url_to_open);
+                            let offer_aria2 = self.offer_aria2();
+                            if !matching.is_empty() || offer_aria2 {
+                                ui.add_space(10.0);
+                            }
+                            for (index, action) in matching.iter().enumerate() {
+                                if index > 0 {
+                                    ui.add_space(5.0);
+                                }
+                                let button = egui::Button::new(egui::RichText::new(&action.name).size(12.0))
+                                    .min_size(egui::vec2(button_width, 28.0));
+                                if ui.add(button).on_hover_text(&action.command).clicked() {
+                                    if let Err(e) = actions::run_action(action, &self.url_to_open) {
+                                        eprintln!("执行动作失败 ({}): {}", action.name, e);
+                                    }
+                                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                                }
+                            }
+
+                            // 直接下载链接：发送到 aria2，结果以toast提示
+                            if offer_aria2 {
+                                if !matching.is_empty() {
+                                    ui.add_space(5.0);
+                                }
+                                let sending = self.aria2_handle.is_some();
+                                let label = if sending { "正在发送到 aria2..." } else { "发送到 aria2" };
+                                let button = egui::Button::new(egui::RichText::new(label).size(12.0))
+                                    .min_size(egui::vec2(button_width, 28.0));
+                                if ui.add_enabled(!sending, button).clicked() {
+                                    if let Some(config) = self.config.aria2.clone() {
+                                        let url = self.url_to_open.clone();
+                                        self.aria2_handle = Some(std::thread::spawn(move || aria2::add_uri(&config, &url)));
+                                    }
+                                }
+                            }
+                        }
+                        if !launched.is_empty() {
+                            self.record_usage(&launched);
                         }
                         // 在所有按钮后添加底部边距
                         ui.add_space(5.0);
@@ -651,38 +2258,25 @@ fn load_icon(path: &str) -> Option<egui::IconData> {
     })
 }
 
-fn main() -> Result<(), eframe::Error> {
-    let args: Vec<String> = std::env::args().collect();
-
-    if args.contains(&"--register".to_string()) {
-        if is_elevated() {
-            match register_app() {
-                Ok(_) => std::process::exit(0),
-                Err(_) => std::process::exit(1),
-            }
-        }
-    } else if args.contains(&"--unregister".to_string()) {
-        if is_elevated() {
-            match unregister_app() {
-                Ok(_) => std::process::exit(0),
-                Err(_) => std::process::exit(1),
-            }
-        }
-    }
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    std::process::exit(cli::run(&args));
+}
 
-    let url_to_open = if args.len() > 1 && !args[1].starts_with("--") {
-        args[1].clone()
-    } else {
-        "https://www.google.com".to_string()
-    };
+// 显示选择窗口；url_to_open 为提取前的原始链接
+fn run_selector(
+    url_to_open: String,
+    all_browsers: Vec<Browser>,
+    config: Config,
+    config_error: Option<String>,
+    private: bool,
+) -> Result<(), eframe::Error> {
+    let (extracted_url, _) = extract_real_url_sync(&url_to_open);
 
-    let all_browsers = get_installed_browsers();
-    
     // 计算初始窗口高度，避免越界
     let mut initial_height = 20.0; // 基础边距
     
     // URL提取状态提示（假设可能有）
-    let (extracted_url, _) = extract_real_url_sync(&url_to_open);
     if extracted_url != url_to_open {
         initial_height += 20.0;
     }
@@ -694,10 +2288,32 @@ fn main() -> Result<(), eframe::Error> {
     initial_height += 20.0;
     
     // 浏览器选项高度
-    let visible_browsers_count = all_browsers.iter().filter(|b| !b.hidden).count();
-    if visible_browsers_count > 0 {
-        initial_height += visible_browsers_count as f32 * 50.0;
-        initial_height += 20.0; // 额外底部边距
+    let visible_browsers_count = all_browsers.iter().filter(|b| config.shows(b)).count();
+    initial_height += browser_buttons_height(visible_browsers_count, config.icon_grid);
+
+    // 媒体播放和应用内打开按钮
+    if config.media.as_ref().map(|m| m.matches_url(&extracted_url)).unwrap_or(false) {
+        initial_height += 50.0;
+    }
+    if deeplink::rewrite(&config.deep_links, &extracted_url).is_some() {
+        initial_height += 50.0;
+    }
+
+    // 浏览器分组按钮
+    let groups_count = config
+        .browser_groups
+        .iter()
+        .filter(|g| !config.group_members(g, &all_browsers).is_empty())
+        .count();
+    initial_height += groups_count as f32 * 50.0;
+
+    // 动作和 aria2 按钮
+    let mut secondary_count = actions::matching_actions(&config.actions, &extracted_url).len();
+    if config.aria2.is_some() && aria2::has_download_extension(&extracted_url) {
+        secondary_count += 1;
+    }
+    if secondary_count > 0 {
+        initial_height += 10.0 + secondary_count as f32 * 33.0;
     }
     
     // 底部边距
@@ -725,6 +2341,364 @@ fn main() -> Result<(), eframe::Error> {
     eframe::run_native(
         "fuckHttp",
         options,
-        Box::new(move |cc| Box::new(BrowserSelectorApp::new(cc, url_to_open, all_browsers))),
+        Box::new(move |cc| {
+            let mut app = BrowserSelectorApp::new(cc, url_to_open, all_browsers, config, config_error);
+            app.private = private;
+            Box::new(app)
+        }),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_qq_and_work_weixin_links() {
+        assert_eq!(
+            extract_real_url_sync("https://c.pc.qq.com/ios.html?level=14&url=https%3A%2F%2Fexample.com%2Fa%3Fb%3D1"),
+            ("https://example.com/a?b=1".to_string(), false)
+        );
+        assert_eq!(
+            extract_real_url_sync("https://open.work.weixin.qq.com/wwopen/uriconfirm?uri=example.com%2Fpage"),
+            ("https://example.com/page".to_string(), false)
+        );
+    }
+
+    #[test]
+    fn wechat_links_need_async_extraction() {
+        let url = "https://weixin110.qq.com/security/readtemplate?t=x";
+        assert_eq!(extract_real_url_sync(url), (url.to_string(), true));
+        // 拦截页面本身不参与路由
+        assert_eq!(routing_url(url), None);
+        assert_eq!(
+            routing_url("https://c.pc.qq.com/ios.html?url=https%3A%2F%2Fexample.com%2F").as_deref(),
+            Some("https://example.com/")
+        );
+    }
+
+    #[test]
+    fn extracts_generic_params_and_fragment() {
+        assert_eq!(
+            extract_real_url_sync("https://link.example.com/jump?target=https%3A%2F%2Fexample.org"),
+            ("https://example.org".to_string(), false)
+        );
+        assert_eq!(
+            extract_real_url_sync("https://link.example.com/#https://example.org/x"),
+            ("https://example.org/x".to_string(), false)
+        );
+        assert_eq!(
+            extract_real_url_sync("https://example.com/?q=rust"),
+            ("https://example.com/?q=rust".to_string(), false)
+        );
+    }
+
+    #[test]
+    fn legacy_config_still_parses() {
+        let config: Config = serde_json::from_str(r#"{ "hidden_browsers": ["Internet Explorer"] }"#).unwrap();
+        assert_eq!(config.settings.hidden_browsers, vec!["Internet Explorer"]);
+        assert!(config.browser_groups.is_empty());
+        assert!(config.aria2.is_none());
+    }
+
+    #[test]
+    fn parses_and_upgrades_config_files() {
+        let (config, version) = parse_config(include_str!("../tests/fixtures/config/v0_hand_edited.json")).unwrap();
+        assert_eq!(version, 0);
+        assert!(config.settings.hidden_browsers.is_empty());
+        assert!(config.actions.is_empty());
+        assert_eq!(config.browser_groups[0].browsers, vec!["Google Chrome", "Firefox"]);
+        assert!(config.icon_grid);
+
+        // 保存的配置带有当前版本，再次读取时不需要升级
+        let content = serde_json::to_string(&VersionedConfig { version: schema::CURRENT_VERSION, config: &config }).unwrap();
+        assert_eq!(parse_config(&content).unwrap().1, schema::CURRENT_VERSION);
+
+        assert!(parse_config("{ \"hidden_browsers\": [").is_err());
+        assert!(parse_config(r#"{ "version": 1, "hidden_browsers": "Firefox" }"#).is_err());
+
+        // 拼错的配置项不会被悄悄忽略
+        let error = parse_config(include_str!("../tests/fixtures/config/v1_unknown_key.json")).err().unwrap();
+        assert!(error.contains("icon_gird"), "{}", error);
+        assert!(error.contains("rules[0].match.host"), "{}", error);
+    }
+
+    #[test]
+    fn invalid_patterns_are_reported_once_loaded() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "actions": [{ "name": "下载", "command": "yt-dlp", "match": { "pattern": "\\.m3u8$" } }],
+                "deep_links": [{ "app": "Slack", "pattern": "[slack", "template": "slack://open" }],
+                "profiles": {
+                    "工作": { "rules": [{ "match": { "pattern": "(unclosed" }, "target": { "browser": "edge" } }] }
+                }
+            }"#,
+        )
+        .unwrap();
+        let pattern = config.actions[0].matches.pattern.as_ref().unwrap();
+        assert!(pattern.is_match("https://example.com/live.m3u8"));
+        assert!(!pattern.is_match("https://example.com/live.mp4"));
+
+        // 无效的表达式不匹配任何链接，保存时原样写回
+        let rule = &config.profiles["工作"].rules[0];
+        assert!(!rule.matches.matches("https://example.com/(unclosed"));
+        let error = config.pattern_error().unwrap();
+        assert!(error.contains("(unclosed") && error.contains("[slack"));
+        assert_eq!(deeplink::rewrite(&config.deep_links, "https://app.slack.com/[slack"), None);
+        assert_eq!(config_value(&config)["profiles"]["工作"]["rules"][0]["match"]["pattern"], "(unclosed");
+    }
+
+    #[test]
+    fn profiles_override_top_level_settings() {
+        let mut config: Config = serde_json::from_str(
+            r#"{
+                "hidden_browsers": ["ie"],
+                "profiles": {
+                    "工作": { "hidden_browsers": ["firefox"], "default_browser": "edge", "disable_network_extraction": true },
+                    "个人": { "hidden_browsers": [], "rules": [{ "match": { "hosts": ["bilibili.com"] }, "target": { "browser": "firefox" } }] }
+                },
+                "active_profile": "工作"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.profile_name(), Some("工作"));
+        assert_eq!(config.profile().hidden_browsers, vec!["firefox"]);
+        assert_eq!(config.profile().default_browser.as_deref(), Some("edge"));
+        assert!(!config.network_extraction_allowed());
+
+        // 命令行指定的方案优先，不存在时仍用配置中的当前方案
+        let url = "https://www.bilibili.com/video/BV1xx";
+        assert_eq!(actions::find_route(&config.profile().rules, url), None);
+        config.profile_override = Some("个人".to_string());
+        assert_eq!(
+            actions::find_route(&config.profile().rules, url),
+            Some(&RouteTarget::Browser("firefox".to_string()))
+        );
+        assert!(config.network_extraction_allowed());
+        config.profile_mut().hidden_browsers.push("opera".to_string());
+        assert_eq!(config.profiles["个人"].hidden_browsers, vec!["opera"]);
+        config.profile_override = Some("不存在".to_string());
+        assert_eq!(config.profile_name(), Some("工作"));
+
+        // 当前方案不存在时使用顶层的默认方案，命令行指定的方案不写入文件
+        config.active_profile = Some("已删除".to_string());
+        assert_eq!(config.profile().hidden_browsers, vec!["ie"]);
+        let saved = config_value(&config);
+        assert_eq!(saved["hidden_browsers"], serde_json::json!(["ie"]));
+        assert_eq!(saved["profiles"]["个人"]["hidden_browsers"], serde_json::json!(["opera"]));
+        assert!(saved.get("profile_override").is_none());
+    }
+
+    #[test]
+    fn config_file_is_shared_between_windows() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        let no_policy = policy::fake::FakePolicySource(Ok(policy::Policy::default()));
+
+        // 两个窗口读取到同样的配置
+        let (mut first, _) = load_config_from(&path, &no_policy);
+        let (mut second, _) = load_config_from(&path, &no_policy);
+
+        first.icon_grid = true;
+        write_config_to(&path, &first).unwrap();
+
+        // 第二个窗口不知道第一个窗口的修改
+        set_config_base(&path, &Config::default());
+        second.aliases.insert("firefox".to_string(), "Firefox (工作)".to_string());
+        write_config_to(&path, &second).unwrap();
+
+        let (saved, error) = load_config_from(&path, &no_policy);
+        assert_eq!(error, None);
+        assert!(saved.icon_grid);
+        assert_eq!(saved.aliases["firefox"], "Firefox (工作)");
+        assert!(paths::config_backup(&path, "1").exists());
+        assert!(!paths::config_backup(&path, "lock").exists());
+
+        // 外部修改后重新加载；文件无效时返回错误
+        let edited = fs::read_to_string(&path).unwrap().replace("\"icon_grid\": true", "\"icon_grid\": false");
+        fs::write(&path, edited).unwrap();
+        assert!(!reload_config_from(&path, &no_policy).unwrap().icon_grid);
+        fs::write(&path, "{ \"version\": 1, ").unwrap();
+        assert!(reload_config_from(&path, &no_policy).is_err());
+
+        // 启动时文件无效则改用最近的备份，即第二个窗口保存之前的版本
+        let (restored, error) = load_config_from(&path, &no_policy);
+        assert!(error.unwrap().contains("config.json.1"));
+        assert!(restored.icon_grid && restored.aliases.is_empty());
+    }
+
+    #[test]
+    fn changed_config_files_trigger_a_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = ConfigFiles {
+            config: dir.path().join("config.json"),
+            layers: vec![dir.path().join("team.json")],
+        };
+        fs::write(&files.layers[0], "{}").unwrap();
+
+        // 配置文件还不存在时不重新加载
+        let stamp = files.stamp();
+        assert_eq!(reload_needed(&stamp, &files.stamp()), None);
+
+        // 配置文件变了，团队预设没变
+        fs::write(&files.config, "{ \"version\": 1 }").unwrap();
+        assert_eq!(reload_needed(&stamp, &files.stamp()), Some(false));
+        let stamp = files.stamp();
+        assert_eq!(reload_needed(&stamp, &files.stamp()), None);
+
+        // 团队预设或策略文件变了
+        fs::write(&files.layers[0], "{ \"rules\": [] }").unwrap();
+        assert_eq!(reload_needed(&stamp, &files.stamp()), Some(true));
+
+        // 后台检查只在文件变化后唤醒窗口
+        let watcher = ConfigWatcher::start(egui::Context::default(), files.clone());
+        std::thread::sleep(CONFIG_CHECK_INTERVAL * 2);
+        assert!(!watcher.take_changed());
+        fs::write(&files.config, "{ \"version\": 1, \"icon_grid\": true }").unwrap();
+        std::thread::sleep(CONFIG_CHECK_INTERVAL * 2);
+        assert!(watcher.take_changed());
+        assert!(!watcher.take_changed());
+    }
+
+    #[test]
+    fn team_preset_entries_are_not_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let preset_path = dir.path().join("team.json");
+        fs::write(
+            &preset_path,
+            r#"{ "version": 1, "hidden_browsers": ["edge"], "actions": [{ "name": "归档", "command": "archive" }] }"#,
+        )
+        .unwrap();
+
+        let config = apply_team_preset(Config {
+            settings: ProfileSettings {
+                hidden_browsers: vec!["ie".to_string()],
+                ..ProfileSettings::default()
+            },
+            team_preset: Some(preset_path),
+            ..Config::default()
+        });
+        assert_eq!(config.preset_error, None);
+        assert_eq!(config.settings.hidden_browsers, vec!["ie", "edge"]);
+        assert_eq!(config.actions[0].name, "归档");
+
+        let own = config_value(&config);
+        assert_eq!(own["hidden_browsers"], serde_json::json!(["ie"]));
+        assert_eq!(own["actions"], serde_json::json!([]));
+
+        let missing = apply_team_preset(Config {
+            team_preset: Some(dir.path().join("missing.json")),
+            ..Config::default()
+        });
+        assert!(missing.preset_error.is_some());
+    }
+
+    #[test]
+    fn browser_groups_are_saved_and_opened() {
+        let browsers = vec![
+            Browser::new("chrome", "Google Chrome"),
+            Browser::new("firefox", "Firefox"),
+            Browser::new("edge", "Microsoft Edge"),
+        ];
+        let mut config = Config::default();
+        config.save_group(" 测试 ", &[&browsers[0], &browsers[1]]);
+
+        // 同名分组被替换，成员按选择的顺序保存
+        config.save_group("测试", &[&browsers[2], &browsers[1], &browsers[0]]);
+        assert_eq!(config.browser_groups.len(), 1);
+        assert_eq!(config.browser_groups[0].name, "测试");
+        assert_eq!(config.browser_groups[0].browsers, vec!["edge", "firefox", "chrome"]);
+
+        let saved: Config = serde_json::from_str(&serde_json::to_string(&config).unwrap()).unwrap();
+        assert_eq!(saved.browser_groups, config.browser_groups);
+
+        // 在所有成员中打开
+        let group = &config.browser_groups[0];
+        let ids = |members: Vec<&Browser>| members.iter().map(|b| b.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(config.group_members(group, &browsers)), vec!["edge", "firefox", "chrome"]);
+
+        // 卸载的浏览器不再打开，但仍留在分组里
+        let installed = vec![browsers[0].clone(), browsers[2].clone()];
+        assert_eq!(ids(config.group_members(group, &installed)), vec!["edge", "chrome"]);
+        assert_eq!(group.browsers.len(), 3);
+
+        // 管理员策略禁止的浏览器也不打开
+        config.policy.blocked_browsers = vec!["Microsoft Edge".to_string()];
+        assert_eq!(ids(config.group_members(&config.browser_groups[0], &installed)), vec!["chrome"]);
+    }
+
+    #[test]
+    fn migrates_name_based_entries_to_ids() {
+        let browsers = vec![
+            Browser::new("google chrome|c:\\chrome.exe", "Google Chrome"),
+            Browser::new("google chrome|c:\\users\\me\\chrome.exe", "Google Chrome"),
+            Browser::new("firefox.desktop|firefox", "Firefox"),
+        ];
+        let mut config: Config = serde_json::from_str(
+            r#"{
+                "hidden_browsers": ["Google Chrome", "Internet Explorer"],
+                "browser_groups": [{ "name": "测试", "browsers": ["firefox.desktop|firefox", "Firefox"] }],
+                "profiles": { "工作": { "hidden_browsers": ["Firefox"] } }
+            }"#,
+        )
+        .unwrap();
+
+        assert!(migrate_config(&mut config, &browsers));
+        assert_eq!(
+            config.settings.hidden_browsers,
+            vec!["google chrome|c:\\chrome.exe", "google chrome|c:\\users\\me\\chrome.exe", "Internet Explorer"]
+        );
+        assert_eq!(config.browser_groups[0].browsers, vec!["firefox.desktop|firefox"]);
+        assert_eq!(config.profiles["工作"].hidden_browsers, vec!["firefox.desktop|firefox"]);
+
+        // 已迁移的配置不再改动
+        assert!(!migrate_config(&mut config, &browsers));
+    }
+
+    #[test]
+    fn migrated_preset_names_are_not_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let preset_path = dir.path().join("team.json");
+        fs::write(
+            &preset_path,
+            r#"{ "version": 1, "hidden_browsers": ["Firefox"], "browser_groups": [{ "name": "测试", "browsers": ["Google Chrome"] }] }"#,
+        )
+        .unwrap();
+        let browsers = vec![
+            Browser::new("google chrome|c:\\chrome.exe", "Google Chrome"),
+            Browser::new("firefox.desktop|firefox", "Firefox"),
+        ];
+
+        let mut config = apply_team_preset(Config {
+            settings: ProfileSettings {
+                hidden_browsers: vec!["Google Chrome".to_string()],
+                ..ProfileSettings::default()
+            },
+            team_preset: Some(preset_path),
+            ..Config::default()
+        });
+        assert!(migrate_config(&mut config, &browsers));
+        assert_eq!(config.settings.hidden_browsers, vec!["google chrome|c:\\chrome.exe", "firefox.desktop|firefox"]);
+        assert_eq!(config.browser_groups[0].browsers, vec!["google chrome|c:\\chrome.exe"]);
+
+        // 迁移后保存的只有用户自己的条目
+        let own = config_value(&config);
+        assert_eq!(own["hidden_browsers"], serde_json::json!(["google chrome|c:\\chrome.exe"]));
+        assert_eq!(own["browser_groups"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn knows_private_mode_flags() {
+        let flag = |command: &str| private_flag(&Browser { command: command.to_string(), ..Browser::new("x", "X") });
+        assert_eq!(flag("\"C:\\Program Files\\Mozilla Firefox\\firefox.exe\" -osint -url \"%1\""), Some("--private-window"));
+        assert_eq!(flag("/usr/bin/google-chrome-stable {url}"), Some("--incognito"));
+        assert_eq!(flag("C:\\Edge\\msedge.exe"), Some("--inprivate"));
+        assert_eq!(flag("flatpak run org.mozilla.firefox {url}"), None);
+    }
+
+    #[test]
+    fn browser_id_uses_executable_only() {
+        assert_eq!(browser_id("Brave", "\"C:\\brave.exe\" --single-argument %1"), "Brave|C:\\brave.exe");
+        assert_eq!(browser_id("firefox.desktop", "firefox {url}"), "firefox.desktop|firefox");
+    }
+}
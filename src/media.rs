@@ -0,0 +1,173 @@
+use crate::actions::{expand_template, has_extension, host_matches};
+use crate::probe::{self, HeadInfo};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+// 内置的视频/音频站点
+const MEDIA_HOSTS: &[&str] = &[
+    "bilibili.com",
+    "b23.tv",
+    "youtube.com",
+    "youtu.be",
+    "douyin.com",
+    "iesdouyin.com",
+];
+
+// 直接媒体文件扩展名
+const MEDIA_EXTENSIONS: &[&str] = &[
+    "m3u8", "mp4", "mkv", "webm", "flv", "mov", "avi", "mp3", "flac", "m4a", "aac", "ogg", "opus",
+    "wav",
+];
+
+// 表示HLS播放列表的Content-Type
+const PLAYLIST_CONTENT_TYPES: &[&str] = &["application/vnd.apple.mpegurl", "application/x-mpegurl"];
+
+fn default_format_arg() -> String {
+    "--ytdl-format={}".to_string()
+}
+
+fn default_cookies_arg() -> String {
+    "--ytdl-raw-options=cookies={}".to_string()
+}
+
+// 站点专用选项，例如登录用的cookies文件或 yt-dlp 格式
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MediaSite {
+    pub hosts: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cookies: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ytdl_format: Option<String>,
+}
+
+// 媒体播放器设置，配置文件中存在该项时媒体链接会在选择窗口顶部显示“▶ 播放”
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MediaConfig {
+    // 播放器命令模板，与动作相同，例如 "mpv {url}"
+    pub player: String,
+    // 内置站点之外的媒体站点
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hosts: Vec<String>,
+    // 域名和扩展名无法判断时是否发送HEAD请求检查Content-Type
+    #[serde(default = "probe::enabled_by_default")]
+    pub probe: bool,
+    // 传递 yt-dlp 格式和cookies文件的参数，{} 为取值位置；默认为 mpv 的写法
+    #[serde(default = "default_format_arg")]
+    pub format_arg: String,
+    #[serde(default = "default_cookies_arg")]
+    pub cookies_arg: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sites: Vec<MediaSite>,
+}
+
+impl MediaConfig {
+    // 仅凭域名和扩展名判断是否为媒体链接
+    pub fn matches_url(&self, url: &str) -> bool {
+        let Ok(parsed) = Url::parse(url) else {
+            return false;
+        };
+        let host = parsed.host_str().unwrap_or("");
+        MEDIA_HOSTS
+            .iter()
+            .copied()
+            .chain(self.hosts.iter().map(String::as_str))
+            .any(|h| host_matches(host, h))
+            || MEDIA_EXTENSIONS.iter().any(|ext| has_extension(parsed.path(), ext))
+    }
+
+    // 结合HEAD响应头判断是否为媒体链接
+    pub fn is_media(&self, url: &str, head: Option<&HeadInfo>) -> bool {
+        if self.matches_url(url) {
+            return true;
+        }
+        match head.and_then(HeadInfo::mime_type) {
+            Some(mime) => {
+                mime.starts_with("video/")
+                    || mime.starts_with("audio/")
+                    || PLAYLIST_CONTENT_TYPES.contains(&mime.as_str())
+            }
+            None => false,
+        }
+    }
+
+    // 播放器命令：模板展开后，在程序名之后插入站点专用参数
+    pub fn player_command(&self, url: &str) -> Vec<String> {
+        let mut args = expand_template(&self.player, url);
+        let host = Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(|h| h.to_string()))
+            .unwrap_or_default();
+
+        if let Some(site) = self.sites.iter().find(|s| s.hosts.iter().any(|h| host_matches(&host, h))) {
+            let mut extra = Vec::new();
+            if let Some(format) = site.ytdl_format.as_deref().filter(|f| !f.is_empty()) {
+                extra.push(self.format_arg.replace("{}", format));
+            }
+            if let Some(cookies) = site.cookies.as_deref().filter(|c| !c.is_empty()) {
+                extra.push(self.cookies_arg.replace("{}", cookies));
+            }
+            let insert_at = args.len().min(1);
+            args.splice(insert_at..insert_at, extra.into_iter().filter(|a| !a.is_empty()));
+        }
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mpv() -> MediaConfig {
+        serde_json::from_str(r#"{ "player": "mpv {url}" }"#).unwrap()
+    }
+
+    #[test]
+    fn detects_media_by_host_and_extension() {
+        let config = mpv();
+        assert!(config.matches_url("https://www.bilibili.com/video/BV1xx411c7mD"));
+        assert!(config.matches_url("https://youtu.be/dQw4w9WgXcQ"));
+        assert!(config.matches_url("https://cdn.example.com/live/index.m3u8?token=1"));
+        assert!(!config.matches_url("https://example.com/video.html"));
+        assert!(config.probe);
+    }
+
+    #[test]
+    fn detects_media_by_content_type() {
+        let config = mpv();
+        let hls = HeadInfo {
+            content_type: Some("application/vnd.apple.mpegurl".to_string()),
+            content_disposition: None,
+        };
+        let html = HeadInfo {
+            content_type: Some("text/html".to_string()),
+            content_disposition: None,
+        };
+        assert!(config.is_media("https://example.com/stream", Some(&hls)));
+        assert!(!config.is_media("https://example.com/stream", Some(&html)));
+        assert!(!config.is_media("https://example.com/stream", None));
+    }
+
+    #[test]
+    fn player_command_adds_site_options() {
+        let mut config = mpv();
+        config.sites.push(MediaSite {
+            hosts: vec!["bilibili.com".to_string()],
+            cookies: Some("C:\\cookies\\bilibili.txt".to_string()),
+            ytdl_format: Some("bv*[height<=1080]+ba".to_string()),
+        });
+
+        assert_eq!(
+            config.player_command("https://www.bilibili.com/video/BV1"),
+            vec![
+                "mpv",
+                "--ytdl-format=bv*[height<=1080]+ba",
+                "--ytdl-raw-options=cookies=C:\\cookies\\bilibili.txt",
+                "https://www.bilibili.com/video/BV1",
+            ]
+        );
+        assert_eq!(
+            config.player_command("https://youtu.be/x"),
+            vec!["mpv", "https://youtu.be/x"]
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use crate::Browser;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// 选择窗口中浏览器的排列方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortMode {
+    // 按设置中拖动调整的顺序
+    #[default]
+    Manual,
+    // 使用次数多的在前
+    Frequency,
+    // 最近使用的在前
+    Recency,
+}
+
+// 浏览器的使用记录
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BrowserUsage {
+    pub count: u64,
+    // Unix 时间戳（秒）
+    pub last_used: u64,
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// 记录一次使用
+pub fn record(usage: &mut BTreeMap<String, BrowserUsage>, id: &str, timestamp: u64) {
+    let entry = usage.entry(id.to_string()).or_default();
+    entry.count += 1;
+    entry.last_used = entry.last_used.max(timestamp);
+}
+
+// 按手动顺序排列：列表中的浏览器在前，新发现的浏览器保持发现顺序排在后面
+pub fn apply_manual_order(browsers: &mut [Browser], order: &[String]) {
+    browsers.sort_by_key(|b| order.iter().position(|id| id == &b.id).unwrap_or(usize::MAX));
+}
+
+// 选择窗口中的顺序；使用记录相同时保持手动顺序
+pub fn sort_for_selector<'a>(
+    mut browsers: Vec<&'a Browser>,
+    mode: SortMode,
+    usage: &BTreeMap<String, BrowserUsage>,
+) -> Vec<&'a Browser> {
+    let usage_of = |b: &Browser| usage.get(&b.id).cloned().unwrap_or_default();
+    match mode {
+        SortMode::Manual => {}
+        SortMode::Frequency => browsers.sort_by_key(|b| std::cmp::Reverse(usage_of(b).count)),
+        SortMode::Recency => browsers.sort_by_key(|b| std::cmp::Reverse(usage_of(b).last_used)),
+    }
+    browsers
+}
+
+// 默认浏览器排在最前面，其余保持原来的顺序
+pub fn default_first(browsers: &mut Vec<&Browser>, default: Option<&str>) {
+    if let Some(index) = browsers.iter().position(|b| Some(b.id.as_str()) == default) {
+        let browser = browsers.remove(index);
+        browsers.insert(0, browser);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn browser(id: &str) -> Browser {
+        Browser::new(id, &id.to_uppercase())
+    }
+
+    fn ids(browsers: &[&Browser]) -> Vec<String> {
+        browsers.iter().map(|b| b.id.clone()).collect()
+    }
+
+    #[test]
+    fn manual_order_keeps_new_browsers_last() {
+        let mut browsers = vec![browser("a"), browser("b"), browser("c"), browser("d")];
+        apply_manual_order(&mut browsers, &["c".to_string(), "gone".to_string(), "a".to_string()]);
+        let order: Vec<_> = browsers.iter().map(|b| b.id.as_str()).collect();
+        assert_eq!(order, vec!["c", "a", "b", "d"]);
+    }
+
+    #[test]
+    fn sorts_by_frequency_and_recency() {
+        let browsers = [browser("a"), browser("b"), browser("c")];
+        let mut usage = BTreeMap::new();
+        record(&mut usage, "b", 100);
+        record(&mut usage, "b", 200);
+        record(&mut usage, "c", 300);
+
+        let all: Vec<&Browser> = browsers.iter().collect();
+        assert_eq!(ids(&sort_for_selector(all.clone(), SortMode::Manual, &usage)), vec!["a", "b", "c"]);
+        assert_eq!(ids(&sort_for_selector(all.clone(), SortMode::Frequency, &usage)), vec!["b", "c", "a"]);
+        assert_eq!(ids(&sort_for_selector(all, SortMode::Recency, &usage)), vec!["c", "b", "a"]);
+        assert_eq!(usage["b"], BrowserUsage { count: 2, last_used: 200 });
+    }
+
+    #[test]
+    fn default_browser_comes_first() {
+        let browsers = [browser("a"), browser("b"), browser("c")];
+        let mut sorted: Vec<&Browser> = browsers.iter().collect();
+        default_first(&mut sorted, Some("c"));
+        assert_eq!(ids(&sorted), vec!["c", "a", "b"]);
+        default_first(&mut sorted, Some("gone"));
+        assert_eq!(ids(&sorted), vec!["c", "a", "b"]);
+    }
+}
@@ -0,0 +1,123 @@
+use crate::platform;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// 覆盖配置目录的环境变量，测试时指向临时目录
+pub const CONFIG_DIR_ENV: &str = "FUCKHTTP_CONFIG_DIR";
+
+const APP_DIR_NAME: &str = "fuckHttp";
+
+// 程序旁边有其中任一文件时为便携模式，所有数据保存在程序旁边的 data 目录
+const PORTABLE_MARKERS: &[&str] = &["portable.flag", "fuckHttp.portable"];
+const PORTABLE_DATA_DIR: &str = "data";
+const CONFIG_FILE: &str = "config.json";
+const CACHE_FILE: &str = "browser_cache.json";
+
+// 旧版本保存在临时目录中的配置
+const LEGACY_CONFIG_FILE: &str = "browser_selector_config.json";
+
+// 当前程序的路径，注册和提权时都使用这个路径
+pub fn exe_path() -> io::Result<PathBuf> {
+    std::env::current_exe()
+}
+
+fn portable_data_dir_in(exe_dir: &Path) -> Option<PathBuf> {
+    PORTABLE_MARKERS
+        .iter()
+        .any(|marker| exe_dir.join(marker).is_file())
+        .then(|| exe_dir.join(PORTABLE_DATA_DIR))
+}
+
+// 便携模式的数据目录，非便携模式时为 None
+pub fn portable_data_dir() -> Option<PathBuf> {
+    portable_data_dir_in(exe_path().ok()?.parent()?)
+}
+
+// 配置目录：便携模式下为程序旁边的 data，否则为 %APPDATA%\fuckHttp 或 $XDG_CONFIG_HOME/fuckHttp
+pub fn config_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os(CONFIG_DIR_ENV).filter(|v| !v.is_empty()) {
+        return PathBuf::from(dir);
+    }
+    if let Some(dir) = portable_data_dir() {
+        return dir;
+    }
+    platform::current()
+        .config_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(APP_DIR_NAME)
+}
+
+pub fn config_file() -> PathBuf {
+    config_dir().join(CONFIG_FILE)
+}
+
+// 配置文件的备份，例如 config.json.invalid
+pub fn config_backup(config_file: &Path, suffix: &str) -> PathBuf {
+    let name = config_file.file_name().map(|n| n.to_string_lossy()).unwrap_or(CONFIG_FILE.into());
+    config_file.with_file_name(format!("{}.{}", name, suffix))
+}
+
+pub fn cache_file() -> PathBuf {
+    config_dir().join(CACHE_FILE)
+}
+
+// 需要迁移的旧配置；配置目录被环境变量覆盖或处于便携模式时不接管本机临时目录中的旧配置
+pub fn legacy_config_file() -> Option<PathBuf> {
+    let overridden = std::env::var_os(CONFIG_DIR_ENV).is_some_and(|v| !v.is_empty());
+    if overridden || portable_data_dir().is_some() {
+        return None;
+    }
+    Some(std::env::temp_dir().join(LEGACY_CONFIG_FILE))
+}
+
+// 把旧位置的配置移到新位置，新位置已有配置时不覆盖；返回是否移动了文件
+pub fn migrate_legacy_config(legacy: &Path, target: &Path) -> io::Result<bool> {
+    if target.exists() || !legacy.is_file() {
+        return Ok(false);
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // 临时目录可能在另一个文件系统上，不能直接 rename
+    fs::copy(legacy, target)?;
+    fs::remove_file(legacy).ok();
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn portable_marker_beside_exe() {
+        let root = tempfile::tempdir().unwrap();
+        assert_eq!(portable_data_dir_in(root.path()), None);
+
+        fs::write(root.path().join("fuckHttp.portable"), "").unwrap();
+        assert_eq!(portable_data_dir_in(root.path()), Some(root.path().join("data")));
+
+        // 同名目录不算标记
+        let other = tempfile::tempdir().unwrap();
+        fs::create_dir(other.path().join("portable.flag")).unwrap();
+        assert_eq!(portable_data_dir_in(other.path()), None);
+    }
+
+    #[test]
+    fn moves_legacy_config_once() {
+        let root = tempfile::tempdir().unwrap();
+        let legacy = root.path().join(LEGACY_CONFIG_FILE);
+        let target = root.path().join("config/fuckHttp").join(CONFIG_FILE);
+        fs::write(&legacy, r#"{ "hidden_browsers": ["a"] }"#).unwrap();
+
+        assert!(migrate_legacy_config(&legacy, &target).unwrap());
+        assert!(!legacy.exists());
+        assert_eq!(fs::read_to_string(&target).unwrap(), r#"{ "hidden_browsers": ["a"] }"#);
+
+        // 新位置已有配置时保留新配置
+        fs::write(&legacy, "{}").unwrap();
+        assert!(!migrate_legacy_config(&legacy, &target).unwrap());
+        assert!(legacy.exists());
+        assert_eq!(fs::read_to_string(&target).unwrap(), r#"{ "hidden_browsers": ["a"] }"#);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::policy::PolicySource;
+use crate::BrowserSource;
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+// 注册表逻辑与平台无关，以便在任何系统上测试；非Windows平台只在测试中使用
+#[cfg_attr(not(windows), allow(dead_code))]
+pub mod registry;
+#[cfg(unix)]
+mod unix;
+#[cfg(windows)]
+mod windows;
+#[cfg(unix)]
+pub mod xdg;
+
+// 平台相关功能：浏览器发现、注册为默认浏览器、提权和字体
+pub trait Platform {
+    fn browser_sources(&self) -> Vec<Box<dyn BrowserSource>>;
+
+    // 注册为 http/https 的处理程序
+    fn register(&self) -> std::io::Result<()>;
+    fn unregister(&self) -> std::io::Result<()>;
+
+    // 是否已注册为 http/https 的处理程序
+    fn is_registered(&self) -> bool;
+
+    // 当前进程是否有注册所需的权限
+    fn is_elevated(&self) -> bool;
+
+    // 以管理员身份重新运行自身并传入参数，返回是否成功
+    fn run_elevated(&self, arg: &str) -> bool;
+
+    // 可显示中文的字体文件，按优先级排列
+    fn font_paths(&self) -> Vec<PathBuf>;
+
+    // 读取浏览器图标，location 为 Browser::icon 的值
+    fn load_icon(&self, location: &str) -> Option<egui::ColorImage>;
+
+    // 程序的产品名称和版本号，用于识别便携版浏览器
+    fn version_info(&self, executable: &Path) -> Option<VersionInfo>;
+
+    // 浏览器来源的状态标记，不变时可以使用上次的发现结果；None 表示无法判断
+    fn discovery_stamp(&self) -> Option<String>;
+
+    // 存放各程序配置的用户目录
+    fn config_dir(&self) -> Option<PathBuf>;
+
+    // 管理员策略的来源
+    fn policy_source(&self) -> Box<dyn PolicySource>;
+
+    // 命令行子命令输出前连接到启动它的控制台
+    fn attach_console(&self);
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VersionInfo {
+    pub product_name: Option<String>,
+    pub version: Option<String>,
+}
+
+// Windows 上可以直接启动的扩展名
+#[cfg(windows)]
+const RUNNABLE_EXTENSIONS: &[&str] = &["exe", "com", "bat", "cmd"];
+
+// 在 PATH 中查找不带路径的程序名
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths).find_map(|dir| {
+        let candidate = dir.join(program);
+        #[cfg(windows)]
+        if candidate.extension().is_none() {
+            return RUNNABLE_EXTENSIONS
+                .iter()
+                .map(|ext| candidate.with_extension(ext))
+                .find(|path| path.is_file());
+        }
+        candidate.is_file().then_some(candidate)
+    })
+}
+
+// 检查浏览器命令中的程序能否启动，返回无法启动的原因
+pub fn check_executable(executable: &str) -> Option<String> {
+    if executable.is_empty() {
+        return Some("命令为空".to_string());
+    }
+
+    let path = Path::new(executable);
+    let path = if path.components().count() > 1 || path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        match find_in_path(executable) {
+            Some(path) => path,
+            None => return Some(format!("PATH 中找不到 {}", executable)),
+        }
+    };
+
+    let metadata = match std::fs::metadata(&path) {
+        Ok(metadata) => metadata,
+        Err(_) => return Some(format!("找不到程序 {}", path.display())),
+    };
+    if !metadata.is_file() {
+        return Some(format!("{} 不是文件", path.display()));
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 == 0 {
+            return Some(format!("{} 没有执行权限", path.display()));
+        }
+    }
+    #[cfg(windows)]
+    {
+        let runnable = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| RUNNABLE_EXTENSIONS.iter().any(|r| ext.eq_ignore_ascii_case(r)));
+        if !runnable {
+            return Some(format!("{} 不是可执行文件", path.display()));
+        }
+    }
+    None
+}
+
+// 读取 PNG、ICO 等图片文件
+fn load_image_file(path: &Path) -> Option<egui::ColorImage> {
+    let image = image::open(path).ok()?.to_rgba8();
+    let size = [image.width() as usize, image.height() as usize];
+    Some(egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw()))
+}
+
+pub fn current() -> &'static dyn Platform {
+    #[cfg(windows)]
+    return &windows::WindowsPlatform;
+    #[cfg(unix)]
+    return &unix::UnixPlatform;
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn reports_why_executable_cannot_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let runnable = dir.path().join("browser");
+        let plain = dir.path().join("readme");
+        std::fs::write(&runnable, "#!/bin/sh\n").unwrap();
+        std::fs::write(&plain, "").unwrap();
+        std::fs::set_permissions(&runnable, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::set_permissions(&plain, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        assert_eq!(check_executable(runnable.to_str().unwrap()), None);
+        assert!(check_executable(plain.to_str().unwrap()).unwrap().contains("没有执行权限"));
+        assert!(check_executable(dir.path().to_str().unwrap()).unwrap().contains("不是文件"));
+        let missing = dir.path().join("missing");
+        assert!(check_executable(missing.to_str().unwrap()).unwrap().contains("找不到程序"));
+        assert_eq!(check_executable("").as_deref(), Some("命令为空"));
+        assert!(check_executable("no-such-browser-xyz").unwrap().contains("PATH"));
+        assert_eq!(check_executable("sh"), None);
+    }
+}
@@ -0,0 +1,833 @@
+use crate::Browser;
+use std::io;
+use std::path::Path;
+
+// 注册表项的基本操作，路径使用反斜杠分隔
+pub trait RegistryKey: Sized {
+    fn open_subkey(&self, path: &str) -> io::Result<Self>;
+    // 以可写方式打开已有的子项
+    fn open_subkey_writable(&self, path: &str) -> io::Result<Self>;
+    // 打开子项，不存在时创建（包括中间各级）
+    fn create_subkey(&self, path: &str) -> io::Result<Self>;
+    fn enum_keys(&self) -> Vec<String>;
+    // 读取字符串值，"" 为默认值
+    fn get_string(&self, name: &str) -> io::Result<String>;
+    fn set_string(&self, name: &str, value: &str) -> io::Result<()>;
+    // 删除子项及其所有内容
+    fn delete_subkey_all(&self, path: &str) -> io::Result<()>;
+    fn delete_value(&self, name: &str) -> io::Result<()>;
+    // 最后写入时间，只反映该项自身的值和直接子项的变化
+    fn last_write_time(&self) -> Option<u64>;
+}
+
+// 注册表根项
+pub trait Registry {
+    type Key: RegistryKey;
+
+    fn local_machine(&self) -> Self::Key;
+    fn current_user(&self) -> Self::Key;
+}
+
+const START_MENU_INTERNET: &str = "SOFTWARE\\Clients\\StartMenuInternet";
+// 64位系统上32位安装程序写入的位置
+const START_MENU_INTERNET_WOW64: &str = "SOFTWARE\\WOW6432Node\\Clients\\StartMenuInternet";
+const APP_PATHS: &str = "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\App Paths";
+
+// 只在 App Paths 中登记的浏览器（常见于按用户安装），按程序名识别
+const KNOWN_BROWSER_EXES: &[(&str, &str)] = &[
+    ("chrome.exe", "Google Chrome"),
+    ("msedge.exe", "Microsoft Edge"),
+    ("firefox.exe", "Firefox"),
+    ("brave.exe", "Brave"),
+    ("opera.exe", "Opera"),
+    ("vivaldi.exe", "Vivaldi"),
+    ("chromium.exe", "Chromium"),
+];
+const CLASSES_KEY: &str = "Software\\Classes\\fuckHttp";
+const APP_KEY: &str = "Software\\fuckHttp";
+const CAPABILITIES_KEY: &str = "Software\\fuckHttp\\Capabilities";
+const REGISTERED_APPS_KEY: &str = "Software\\RegisteredApplications";
+
+// 浏览器的 DefaultIcon，没有时使用启动命令中可执行文件的第一个图标
+fn browser_icon<K: RegistryKey>(key: &K, command: &str) -> Option<String> {
+    key.open_subkey("DefaultIcon")
+        .and_then(|icon_key| icon_key.get_string(""))
+        .ok()
+        .filter(|icon| !icon.trim().is_empty())
+        .or_else(|| {
+            let executable = crate::command_executable(command);
+            (!executable.is_empty()).then(|| format!("\"{}\",0", executable))
+        })
+}
+
+// 解析 "路径,索引" 形式的图标位置，路径可带引号；负索引表示资源ID
+pub fn parse_icon_location(location: &str) -> (String, i32) {
+    let location = location.trim();
+    let (path, index) = match location.rsplit_once(',') {
+        Some((path, index)) if index.trim().parse::<i32>().is_ok() => {
+            (path.trim(), index.trim().parse().unwrap_or(0))
+        }
+        _ => (location, 0),
+    };
+    (expand_env_vars(path.trim_matches('"')), index)
+}
+
+// 展开 REG_EXPAND_SZ 中的 %变量%，未定义的变量保持原样
+fn expand_env_vars(value: &str) -> String {
+    let mut result = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find('%') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('%') {
+            Some(end) => {
+                let name = &after[..end];
+                match std::env::var(name) {
+                    Ok(expanded) if !name.is_empty() => result.push_str(&expanded),
+                    _ => {
+                        result.push('%');
+                        result.push_str(name);
+                        result.push('%');
+                    }
+                }
+                rest = &after[end + 1..];
+            }
+            None => {
+                result.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+// 读取某个根项下 StartMenuInternet（包括 WOW6432Node）中注册的浏览器，按ID去重
+pub fn get_browsers_from_hive<K: RegistryKey>(hive: &K, browsers: &mut Vec<Browser>) {
+    for path in [START_MENU_INTERNET, START_MENU_INTERNET_WOW64] {
+        get_browsers_from_key(hive, path, browsers);
+    }
+}
+
+fn get_browsers_from_key<K: RegistryKey>(hive: &K, path: &str, browsers: &mut Vec<Browser>) {
+    if let Ok(key) = hive.open_subkey(path) {
+        for subkey_name in key.enum_keys() {
+            if let Ok(subkey) = key.open_subkey(&subkey_name) {
+                if let Ok(name) = subkey.get_string("") {
+                    if let Ok(command_key) = subkey.open_subkey("shell\\open\\command") {
+                        if let Ok(command) = command_key.get_string("") {
+                            // 注册表和路径不区分大小写
+                            let id = crate::browser_id(&subkey_name, &command).to_lowercase();
+                            if !browsers.iter().any(|b| b.id == id) {
+                                let icon = browser_icon(&subkey, &command);
+                                browsers.push(Browser {
+                                    id,
+                                    name,
+                                    command,
+                                    hidden: false,
+                                    icon,
+                                    broken: None,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// App Paths 中登记的已知浏览器，跳过已经发现的同一程序
+fn get_browsers_from_app_paths<K: RegistryKey>(hive: &K, browsers: &mut Vec<Browser>) {
+    let Ok(key) = hive.open_subkey(APP_PATHS) else {
+        return;
+    };
+    for (exe, name) in KNOWN_BROWSER_EXES {
+        let Ok(executable) = key.open_subkey(exe).and_then(|subkey| subkey.get_string("")) else {
+            continue;
+        };
+        let executable = executable.trim().trim_matches('"');
+        if executable.is_empty()
+            || browsers
+                .iter()
+                .any(|b| crate::command_executable(&b.command).eq_ignore_ascii_case(executable))
+        {
+            continue;
+        }
+        let command = format!("\"{}\"", executable);
+        browsers.push(Browser {
+            id: crate::browser_id(&format!("App Paths\\{}", exe), &command).to_lowercase(),
+            name: name.to_string(),
+            icon: Some(format!("{},0", command)),
+            command,
+            hidden: false,
+            broken: None,
+        });
+    }
+}
+
+// 浏览器相关注册表项的最后写入时间，任何一项变化都说明浏览器列表可能变了
+pub fn discovery_stamp<R: Registry>(registry: &R) -> String {
+    let mut times = Vec::new();
+    for hive in [registry.local_machine(), registry.current_user()] {
+        for path in [START_MENU_INTERNET, START_MENU_INTERNET_WOW64, APP_PATHS] {
+            let Ok(key) = hive.open_subkey(path) else {
+                times.push("-".to_string());
+                continue;
+            };
+            times.push(key.last_write_time().unwrap_or(0).to_string());
+            // 浏览器更新时通常只改动子项中的命令
+            if path != APP_PATHS {
+                for subkey_name in key.enum_keys() {
+                    for subpath in [subkey_name.clone(), format!("{}\\shell\\open\\command", subkey_name)] {
+                        let time = key.open_subkey(&subpath).ok().and_then(|k| k.last_write_time());
+                        times.push(time.unwrap_or(0).to_string());
+                    }
+                }
+            }
+        }
+    }
+    times.join(",")
+}
+
+// HKLM 和 HKCU 中注册的浏览器
+pub fn get_installed_browsers<R: Registry>(registry: &R) -> Vec<Browser> {
+    let mut browsers = Vec::new();
+    get_browsers_from_hive(&registry.local_machine(), &mut browsers);
+    get_browsers_from_hive(&registry.current_user(), &mut browsers);
+    get_browsers_from_app_paths(&registry.current_user(), &mut browsers);
+    get_browsers_from_app_paths(&registry.local_machine(), &mut browsers);
+    browsers
+}
+
+#[derive(Clone, Copy)]
+enum Hive {
+    LocalMachine,
+    CurrentUser,
+}
+
+impl Hive {
+    fn key<R: Registry>(self, registry: &R) -> R::Key {
+        match self {
+            Hive::LocalMachine => registry.local_machine(),
+            Hive::CurrentUser => registry.current_user(),
+        }
+    }
+}
+
+// 注册时的一次写入，撤销时据此恢复
+enum Change {
+    // 本次新建的项（已存在的上级不算），撤销时整个删除
+    CreatedKey(Hive, String),
+    // 写入前的值，None 表示原来没有这个值
+    Value(Hive, String, String, Option<String>),
+}
+
+// 记录注册过程中的写入，失败时只撤销本次的改动，保留之前可用的注册信息
+struct Journal<'a, R: Registry> {
+    registry: &'a R,
+    changes: Vec<Change>,
+}
+
+impl<'a, R: Registry> Journal<'a, R> {
+    fn new(registry: &'a R) -> Self {
+        Self {
+            registry,
+            changes: Vec::new(),
+        }
+    }
+
+    // 创建子项（包括中间各级），记录原来不存在的最上一级
+    fn create(&mut self, hive: Hive, path: &str) -> io::Result<()> {
+        let root = hive.key(self.registry);
+        let mut current = String::new();
+        for segment in path.split('\\') {
+            if !current.is_empty() {
+                current.push('\\');
+            }
+            current.push_str(segment);
+            if root.open_subkey(&current).is_err() {
+                self.changes.push(Change::CreatedKey(hive, current));
+                break;
+            }
+        }
+        root.create_subkey(path).map(|_| ())
+    }
+
+    fn set(&mut self, hive: Hive, path: &str, name: &str, value: &str) -> io::Result<()> {
+        let key = hive.key(self.registry).open_subkey_writable(path)?;
+        let previous = key.get_string(name).ok();
+        self.changes
+            .push(Change::Value(hive, path.to_string(), name.to_string(), previous));
+        key.set_string(name, value)
+    }
+
+    // 按相反顺序撤销，尽量恢复，忽略撤销中的错误
+    fn rollback(self) {
+        for change in self.changes.into_iter().rev() {
+            match change {
+                Change::CreatedKey(hive, path) => {
+                    hive.key(self.registry).delete_subkey_all(&path).ok();
+                }
+                Change::Value(hive, path, name, previous) => {
+                    if let Ok(key) = hive.key(self.registry).open_subkey_writable(&path) {
+                        match previous {
+                            Some(value) => key.set_string(&name, &value).ok(),
+                            None => key.delete_value(&name).ok(),
+                        };
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn write_registration<R: Registry>(journal: &mut Journal<R>, exe_path: &Path) -> io::Result<()> {
+    let exe_path = exe_path.display();
+    let icon_path = format!("{}\\DefaultIcon", CLASSES_KEY);
+    let command_path = format!("{}\\shell\\open\\command", CLASSES_KEY);
+    let url_assoc_path = format!("{}\\URLAssociations", CAPABILITIES_KEY);
+    let hkcu = Hive::CurrentUser;
+    let hklm = Hive::LocalMachine;
+
+    journal.create(hkcu, CLASSES_KEY)?;
+    journal.set(hkcu, CLASSES_KEY, "", "URL:fuckHttp Protocol")?;
+    journal.set(hkcu, CLASSES_KEY, "URL Protocol", "")?;
+
+    journal.create(hkcu, &icon_path)?;
+    journal.set(hkcu, &icon_path, "", &format!("\"{}\",0", exe_path))?;
+
+    journal.create(hkcu, &command_path)?;
+    journal.set(hkcu, &command_path, "", &format!("\"{}\" \"%1\"", exe_path))?;
+
+    journal.create(hklm, CAPABILITIES_KEY)?;
+    journal.set(hklm, CAPABILITIES_KEY, "ApplicationName", "fuckHttp")?;
+    journal.set(hklm, CAPABILITIES_KEY, "ApplicationIcon", &format!("\"{}\",0", exe_path))?;
+    journal.set(hklm, CAPABILITIES_KEY, "ApplicationDescription", "A custom browser selector.")?;
+
+    journal.create(hklm, &url_assoc_path)?;
+    journal.set(hklm, &url_assoc_path, "http", "fuckHttp")?;
+    journal.set(hklm, &url_assoc_path, "https", "fuckHttp")?;
+
+    // RegisteredApplications 由系统创建，不存在时说明环境异常，不自行创建
+    journal.set(hklm, REGISTERED_APPS_KEY, "fuckHttp", CAPABILITIES_KEY)?;
+
+    Ok(())
+}
+
+// 注册 fuckHttp 协议和 http/https 能力；中途失败时撤销本次写入的内容，恢复原有的注册信息
+pub fn register_app<R: Registry>(registry: &R, exe_path: &Path) -> io::Result<()> {
+    let mut journal = Journal::new(registry);
+    let result = write_registration(&mut journal, exe_path);
+    if result.is_err() {
+        journal.rollback();
+    }
+    result
+}
+
+// 是否已注册：协议命令和 RegisteredApplications 中的记录都存在
+pub fn is_registered<R: Registry>(registry: &R) -> bool {
+    let command = registry
+        .current_user()
+        .open_subkey(&format!("{}\\shell\\open\\command", CLASSES_KEY))
+        .and_then(|key| key.get_string(""));
+    let capabilities = registry
+        .local_machine()
+        .open_subkey(REGISTERED_APPS_KEY)
+        .and_then(|key| key.get_string("fuckHttp"));
+    command.is_ok() && capabilities.is_ok()
+}
+
+// 不存在的项视为已删除
+fn ignore_not_found(result: io::Result<()>) -> io::Result<()> {
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        other => other,
+    }
+}
+
+// 删除注册信息；某一步失败时仍尝试其余步骤，返回第一个错误
+pub fn unregister_app<R: Registry>(registry: &R) -> io::Result<()> {
+    let hkcu = registry.current_user();
+    let hklm = registry.local_machine();
+
+    let results = [
+        ignore_not_found(hkcu.delete_subkey_all(CLASSES_KEY)),
+        ignore_not_found(hklm.delete_subkey_all(APP_KEY)),
+        ignore_not_found(
+            hklm.open_subkey_writable(REGISTERED_APPS_KEY)
+                .and_then(|key| key.delete_value("fuckHttp")),
+        ),
+    ];
+    results.into_iter().collect()
+}
+
+// 用于测试的内存注册表
+#[cfg(test)]
+pub mod fake {
+    use super::{Registry, RegistryKey};
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+    use std::io;
+    use std::rc::Rc;
+
+    #[derive(Default)]
+    struct Node {
+        name: String,
+        values: BTreeMap<String, String>,
+        // 最后写入的逻辑时间
+        modified: u64,
+    }
+
+    #[derive(Default)]
+    struct Tree {
+        // 小写的完整路径 -> 项；注册表不区分大小写
+        nodes: BTreeMap<String, Node>,
+        // 禁止写入的路径前缀（小写），模拟没有管理员权限
+        read_only: Vec<String>,
+        // 每次写入递增，作为最后写入时间
+        clock: u64,
+    }
+
+    impl Tree {
+        fn touch(&mut self, path: &str) {
+            self.clock += 1;
+            let clock = self.clock;
+            if let Some(node) = self.nodes.get_mut(path) {
+                node.modified = clock;
+            }
+        }
+    }
+
+    fn parent_of(path: &str) -> &str {
+        path.rsplit_once('\\').map(|(parent, _)| parent).unwrap_or("")
+    }
+
+    #[derive(Clone, Default)]
+    pub struct FakeRegistry {
+        tree: Rc<RefCell<Tree>>,
+    }
+
+    #[derive(Clone)]
+    pub struct FakeKey {
+        tree: Rc<RefCell<Tree>>,
+        path: String,
+    }
+
+    fn normalize(path: &str) -> String {
+        path.trim_matches('\\').to_lowercase()
+    }
+
+    fn join(parent: &str, child: &str) -> String {
+        let child = child.trim_matches('\\');
+        if child.is_empty() {
+            parent.to_string()
+        } else {
+            format!("{}\\{}", parent, child)
+        }
+    }
+
+    fn not_found() -> io::Error {
+        io::Error::new(io::ErrorKind::NotFound, "registry key not found")
+    }
+
+    impl FakeRegistry {
+        pub fn new() -> Self {
+            let registry = Self::default();
+            for root in ["HKEY_LOCAL_MACHINE", "HKEY_CURRENT_USER"] {
+                registry.tree.borrow_mut().nodes.insert(
+                    normalize(root),
+                    Node {
+                        name: root.to_string(),
+                        ..Node::default()
+                    },
+                );
+            }
+            registry
+        }
+
+        // 按完整路径设置字符串值，例如 "HKEY_LOCAL_MACHINE\\SOFTWARE\\x"
+        pub fn set(&self, path: &str, name: &str, value: &str) {
+            let (root, rest) = path.split_once('\\').unwrap_or((path, ""));
+            let key = self.root(root).create_subkey(rest).unwrap();
+            key.set_string(name, value).unwrap();
+        }
+
+        pub fn get(&self, path: &str, name: &str) -> Option<String> {
+            let tree = self.tree.borrow();
+            tree.nodes
+                .get(&normalize(path))
+                .and_then(|node| node.values.get(&name.to_lowercase()).cloned())
+        }
+
+        pub fn exists(&self, path: &str) -> bool {
+            self.tree.borrow().nodes.contains_key(&normalize(path))
+        }
+
+        pub fn deny_writes(&self, path_prefix: &str) {
+            self.tree.borrow_mut().read_only.push(normalize(path_prefix));
+        }
+
+        fn root(&self, name: &str) -> FakeKey {
+            FakeKey {
+                tree: self.tree.clone(),
+                path: normalize(name),
+            }
+        }
+    }
+
+    impl Registry for FakeRegistry {
+        type Key = FakeKey;
+
+        fn local_machine(&self) -> FakeKey {
+            self.root("HKEY_LOCAL_MACHINE")
+        }
+
+        fn current_user(&self) -> FakeKey {
+            self.root("HKEY_CURRENT_USER")
+        }
+    }
+
+    impl FakeKey {
+        fn check_writable(&self, path: &str) -> io::Result<()> {
+            let tree = self.tree.borrow();
+            if tree
+                .read_only
+                .iter()
+                .any(|prefix| path == prefix || path.starts_with(&format!("{}\\", prefix)))
+            {
+                return Err(io::Error::new(io::ErrorKind::PermissionDenied, "access denied"));
+            }
+            Ok(())
+        }
+
+        fn child(&self, path: &str) -> FakeKey {
+            FakeKey {
+                tree: self.tree.clone(),
+                path: normalize(&join(&self.path, path)),
+            }
+        }
+    }
+
+    impl RegistryKey for FakeKey {
+        fn open_subkey(&self, path: &str) -> io::Result<Self> {
+            let child = self.child(path);
+            if self.tree.borrow().nodes.contains_key(&child.path) {
+                Ok(child)
+            } else {
+                Err(not_found())
+            }
+        }
+
+        fn open_subkey_writable(&self, path: &str) -> io::Result<Self> {
+            let child = self.open_subkey(path)?;
+            child.check_writable(&child.path)?;
+            Ok(child)
+        }
+
+        fn create_subkey(&self, path: &str) -> io::Result<Self> {
+            let mut current = self.path.clone();
+            for segment in path.split('\\').filter(|s| !s.is_empty()) {
+                current = join(&current, &segment.to_lowercase());
+                if !self.tree.borrow().nodes.contains_key(&current) {
+                    self.check_writable(&current)?;
+                    let mut tree = self.tree.borrow_mut();
+                    tree.nodes.insert(
+                        current.clone(),
+                        Node {
+                            name: segment.to_string(),
+                            ..Node::default()
+                        },
+                    );
+                    tree.touch(&current);
+                    tree.touch(parent_of(&current));
+                }
+            }
+            Ok(self.child(path))
+        }
+
+        fn enum_keys(&self) -> Vec<String> {
+            let prefix = format!("{}\\", self.path);
+            let tree = self.tree.borrow();
+            tree.nodes
+                .iter()
+                .filter(|(path, _)| path.starts_with(&prefix) && !path[prefix.len()..].contains('\\'))
+                .map(|(_, node)| node.name.clone())
+                .collect()
+        }
+
+        fn get_string(&self, name: &str) -> io::Result<String> {
+            let tree = self.tree.borrow();
+            tree.nodes
+                .get(&self.path)
+                .and_then(|node| node.values.get(&name.to_lowercase()).cloned())
+                .ok_or_else(not_found)
+        }
+
+        fn set_string(&self, name: &str, value: &str) -> io::Result<()> {
+            self.check_writable(&self.path)?;
+            let mut tree = self.tree.borrow_mut();
+            let node = tree.nodes.get_mut(&self.path).ok_or_else(not_found)?;
+            node.values.insert(name.to_lowercase(), value.to_string());
+            tree.touch(&self.path);
+            Ok(())
+        }
+
+        fn delete_subkey_all(&self, path: &str) -> io::Result<()> {
+            let target = self.child(path).path;
+            self.check_writable(&target)?;
+            let mut tree = self.tree.borrow_mut();
+            if !tree.nodes.contains_key(&target) {
+                return Err(not_found());
+            }
+            let prefix = format!("{}\\", target);
+            tree.nodes.retain(|p, _| p != &target && !p.starts_with(&prefix));
+            tree.touch(parent_of(&target));
+            Ok(())
+        }
+
+        fn delete_value(&self, name: &str) -> io::Result<()> {
+            self.check_writable(&self.path)?;
+            let mut tree = self.tree.borrow_mut();
+            let node = tree.nodes.get_mut(&self.path).ok_or_else(not_found)?;
+            node.values.remove(&name.to_lowercase()).ok_or_else(not_found)?;
+            tree.touch(&self.path);
+            Ok(())
+        }
+
+        fn last_write_time(&self) -> Option<u64> {
+            self.tree.borrow().nodes.get(&self.path).map(|node| node.modified)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fake::FakeRegistry;
+    use super::*;
+    use std::path::Path;
+
+    const HKLM_BROWSERS: &str = "HKEY_LOCAL_MACHINE\\SOFTWARE\\Clients\\StartMenuInternet";
+    const HKCU_BROWSERS: &str = "HKEY_CURRENT_USER\\SOFTWARE\\Clients\\StartMenuInternet";
+
+    fn add_browser(registry: &FakeRegistry, hive_path: &str, key: &str, name: &str, command: &str) {
+        registry.set(&format!("{}\\{}", hive_path, key), "", name);
+        registry.set(&format!("{}\\{}\\shell\\open\\command", hive_path, key), "", command);
+    }
+
+    // 模拟 Windows 预先存在的 RegisteredApplications 项
+    fn windows_registry() -> FakeRegistry {
+        let registry = FakeRegistry::new();
+        registry.set("HKEY_LOCAL_MACHINE\\Software\\RegisteredApplications", "Other", "x");
+        registry
+    }
+
+    #[test]
+    fn discovers_browsers_from_both_hives() {
+        let registry = FakeRegistry::new();
+        add_browser(&registry, HKLM_BROWSERS, "Google Chrome", "Google Chrome", "\"C:\\chrome.exe\"");
+        add_browser(&registry, HKLM_BROWSERS, "Firefox-308046B0AF4A39CB", "Firefox", "\"C:\\firefox.exe\"");
+        add_browser(&registry, HKCU_BROWSERS, "Google Chrome", "Google Chrome", "\"C:\\Users\\me\\chrome.exe\"");
+        add_browser(&registry, HKCU_BROWSERS, "Brave", "Brave", "\"C:\\Users\\me\\brave.exe\"");
+        // 两个根项中指向同一程序的项只保留一个
+        add_browser(&registry, HKCU_BROWSERS, "FIREFOX-308046B0AF4A39CB", "Firefox", "\"C:\\Firefox.exe\" -osint");
+        // 缺少命令的残留项被忽略
+        registry.set(&format!("{}\\Broken", HKCU_BROWSERS), "", "Broken");
+
+        let browsers = get_installed_browsers(&registry);
+        let found: Vec<_> = browsers.iter().map(|b| (b.id.as_str(), b.name.as_str())).collect();
+        assert_eq!(
+            found,
+            vec![
+                ("firefox-308046b0af4a39cb|c:\\firefox.exe", "Firefox"),
+                ("google chrome|c:\\chrome.exe", "Google Chrome"),
+                ("brave|c:\\users\\me\\brave.exe", "Brave"),
+                ("google chrome|c:\\users\\me\\chrome.exe", "Google Chrome"),
+            ]
+        );
+    }
+
+    #[test]
+    fn discovers_wow64_and_app_paths_browsers() {
+        let registry = FakeRegistry::new();
+        let wow64 = "HKEY_LOCAL_MACHINE\\SOFTWARE\\WOW6432Node\\Clients\\StartMenuInternet";
+        let app_paths = "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\App Paths";
+        add_browser(&registry, wow64, "Opera", "Opera", "\"C:\\Program Files (x86)\\Opera\\opera.exe\"");
+        // 已通过 StartMenuInternet 发现的程序不重复添加
+        registry.set(&format!("HKEY_LOCAL_MACHINE\\{}\\opera.exe", app_paths), "", "C:\\Program Files (x86)\\Opera\\Opera.exe");
+        registry.set(
+            &format!("HKEY_CURRENT_USER\\{}\\chrome.exe", app_paths),
+            "",
+            "\"C:\\Users\\me\\AppData\\Local\\Google\\Chrome\\Application\\chrome.exe\"",
+        );
+        // 不认识的程序忽略
+        registry.set(&format!("HKEY_CURRENT_USER\\{}\\notepad.exe", app_paths), "", "C:\\notepad.exe");
+
+        let browsers = get_installed_browsers(&registry);
+        let found: Vec<_> = browsers.iter().map(|b| (b.name.as_str(), b.command.as_str())).collect();
+        assert_eq!(
+            found,
+            vec![
+                ("Opera", "\"C:\\Program Files (x86)\\Opera\\opera.exe\""),
+                ("Google Chrome", "\"C:\\Users\\me\\AppData\\Local\\Google\\Chrome\\Application\\chrome.exe\""),
+            ]
+        );
+        assert_eq!(
+            browsers[1].id,
+            "app paths\\chrome.exe|c:\\users\\me\\appdata\\local\\google\\chrome\\application\\chrome.exe"
+        );
+    }
+
+    #[test]
+    fn discovery_stamp_changes_with_browser_keys() {
+        let registry = FakeRegistry::new();
+        add_browser(&registry, HKLM_BROWSERS, "Firefox", "Firefox", "\"C:\\firefox.exe\"");
+        let stamp = discovery_stamp(&registry);
+        assert_eq!(discovery_stamp(&registry), stamp);
+
+        // 更新后的命令
+        registry.set(&format!("{}\\Firefox\\shell\\open\\command", HKLM_BROWSERS), "", "\"D:\\firefox.exe\"");
+        let updated = discovery_stamp(&registry);
+        assert_ne!(updated, stamp);
+
+        // 新安装的浏览器
+        add_browser(&registry, HKCU_BROWSERS, "Brave", "Brave", "\"C:\\brave.exe\"");
+        assert_ne!(discovery_stamp(&registry), updated);
+    }
+
+    #[test]
+    fn reads_default_icon_or_falls_back_to_executable() {
+        let registry = FakeRegistry::new();
+        add_browser(&registry, HKLM_BROWSERS, "Firefox", "Firefox", "\"C:\\firefox.exe\" -osint");
+        registry.set(&format!("{}\\Firefox\\DefaultIcon", HKLM_BROWSERS), "", "C:\\firefox.exe,-101");
+        add_browser(&registry, HKLM_BROWSERS, "Opera", "Opera", "\"C:\\opera\\launcher.exe\"");
+
+        let browsers = get_installed_browsers(&registry);
+        let icons: Vec<_> = browsers.iter().map(|b| b.icon.as_deref()).collect();
+        assert_eq!(
+            icons,
+            vec![Some("C:\\firefox.exe,-101"), Some("\"C:\\opera\\launcher.exe\",0")]
+        );
+    }
+
+    #[test]
+    fn parses_icon_locations() {
+        assert_eq!(parse_icon_location("\"C:\\a b\\x.exe\",0"), ("C:\\a b\\x.exe".to_string(), 0));
+        assert_eq!(parse_icon_location("C:\\x.exe,-101"), ("C:\\x.exe".to_string(), -101));
+        assert_eq!(parse_icon_location("C:\\icons\\x.ico"), ("C:\\icons\\x.ico".to_string(), 0));
+        assert_eq!(parse_icon_location("%NO_SUCH_VAR_X%\\x.exe, 2"), ("%NO_SUCH_VAR_X%\\x.exe".to_string(), 2));
+    }
+
+    #[test]
+    fn register_writes_protocol_and_capabilities() {
+        let registry = windows_registry();
+        assert!(!is_registered(&registry));
+        register_app(&registry, Path::new("C:\\Tools\\fuckHttp.exe")).unwrap();
+        assert!(is_registered(&registry));
+
+        assert_eq!(
+            registry.get("HKEY_CURRENT_USER\\Software\\Classes\\fuckHttp\\shell\\open\\command", ""),
+            Some("\"C:\\Tools\\fuckHttp.exe\" \"%1\"".to_string())
+        );
+        assert_eq!(
+            registry.get("HKEY_LOCAL_MACHINE\\Software\\fuckHttp\\Capabilities\\URLAssociations", "https"),
+            Some("fuckHttp".to_string())
+        );
+        assert_eq!(
+            registry.get("HKEY_LOCAL_MACHINE\\Software\\RegisteredApplications", "fuckHttp"),
+            Some(CAPABILITIES_KEY.to_string())
+        );
+
+        unregister_app(&registry).unwrap();
+        assert!(!is_registered(&registry));
+        assert!(!registry.exists("HKEY_CURRENT_USER\\Software\\Classes\\fuckHttp"));
+        assert!(!registry.exists("HKEY_LOCAL_MACHINE\\Software\\fuckHttp"));
+        assert_eq!(registry.get("HKEY_LOCAL_MACHINE\\Software\\RegisteredApplications", "fuckHttp"), None);
+        assert_eq!(
+            registry.get("HKEY_LOCAL_MACHINE\\Software\\RegisteredApplications", "Other"),
+            Some("x".to_string())
+        );
+    }
+
+    #[test]
+    fn register_rolls_back_when_machine_hive_is_read_only() {
+        let registry = windows_registry();
+        registry.deny_writes("HKEY_LOCAL_MACHINE");
+
+        let err = register_app(&registry, Path::new("C:\\fuckHttp.exe")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(!registry.exists("HKEY_CURRENT_USER\\Software\\Classes\\fuckHttp"));
+    }
+
+    #[test]
+    fn register_rolls_back_when_registered_applications_is_missing() {
+        let registry = FakeRegistry::new();
+
+        let err = register_app(&registry, Path::new("C:\\fuckHttp.exe")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(!registry.exists("HKEY_CURRENT_USER\\Software\\Classes\\fuckHttp"));
+        assert!(!registry.exists("HKEY_LOCAL_MACHINE\\Software\\fuckHttp"));
+    }
+
+    #[test]
+    fn failed_register_keeps_previous_registration() {
+        let registry = windows_registry();
+        register_app(&registry, Path::new("C:\\Old\\fuckHttp.exe")).unwrap();
+        registry.set("HKEY_CURRENT_USER\\Software\\Classes\\fuckHttp", "Extra", "kept");
+        registry.deny_writes("HKEY_LOCAL_MACHINE");
+
+        let err = register_app(&registry, Path::new("C:\\New\\fuckHttp.exe")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        // 本次改写的值恢复原样，原来的注册信息仍然可用
+        assert!(is_registered(&registry));
+        assert_eq!(
+            registry.get("HKEY_CURRENT_USER\\Software\\Classes\\fuckHttp\\shell\\open\\command", ""),
+            Some("\"C:\\Old\\fuckHttp.exe\" \"%1\"".to_string())
+        );
+        assert_eq!(
+            registry.get("HKEY_CURRENT_USER\\Software\\Classes\\fuckHttp", "Extra"),
+            Some("kept".to_string())
+        );
+    }
+
+    #[test]
+    fn failed_register_removes_only_values_it_added() {
+        let registry = windows_registry();
+        // 已有的协议项中原来没有 URL Protocol 值
+        registry.set("HKEY_CURRENT_USER\\Software\\Classes\\fuckHttp", "", "旧的描述");
+        registry.deny_writes("HKEY_LOCAL_MACHINE");
+
+        register_app(&registry, Path::new("C:\\fuckHttp.exe")).unwrap_err();
+        assert!(registry.exists("HKEY_CURRENT_USER\\Software\\Classes\\fuckHttp"));
+        assert_eq!(
+            registry.get("HKEY_CURRENT_USER\\Software\\Classes\\fuckHttp", ""),
+            Some("旧的描述".to_string())
+        );
+        assert_eq!(registry.get("HKEY_CURRENT_USER\\Software\\Classes\\fuckHttp", "URL Protocol"), None);
+        assert!(!registry.exists("HKEY_CURRENT_USER\\Software\\Classes\\fuckHttp\\shell"));
+    }
+
+    #[test]
+    fn unregister_tolerates_missing_keys() {
+        let registry = windows_registry();
+        unregister_app(&registry).unwrap();
+
+        // 只残留了部分注册信息时也能清理干净
+        registry.set("HKEY_LOCAL_MACHINE\\Software\\fuckHttp\\Capabilities", "ApplicationName", "fuckHttp");
+        unregister_app(&registry).unwrap();
+        assert!(!registry.exists("HKEY_LOCAL_MACHINE\\Software\\fuckHttp"));
+    }
+
+    #[test]
+    fn unregister_continues_after_partial_failure() {
+        let registry = windows_registry();
+        register_app(&registry, Path::new("C:\\fuckHttp.exe")).unwrap();
+        registry.deny_writes("HKEY_LOCAL_MACHINE");
+
+        let err = unregister_app(&registry).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        // HKCU 部分不受 HKLM 权限影响，仍被删除
+        assert!(!registry.exists("HKEY_CURRENT_USER\\Software\\Classes\\fuckHttp"));
+        assert!(registry.exists("HKEY_LOCAL_MACHINE\\Software\\fuckHttp"));
+    }
+}
@@ -0,0 +1,111 @@
+use super::xdg;
+use super::{load_image_file, Platform, VersionInfo};
+use crate::policy::{FilePolicySource, PolicySource, POLICY_FILE};
+use crate::{paths, BrowserSource};
+use eframe::egui;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub struct UnixPlatform;
+
+// 便携模式下注册记录也保存在程序旁边
+fn xdg_dirs() -> std::io::Result<xdg::XdgDirs> {
+    let mut dirs = xdg::XdgDirs::from_env()?;
+    if let Some(data_dir) = paths::portable_data_dir() {
+        dirs.state_dir = data_dir;
+    }
+    Ok(dirs)
+}
+
+// 常见发行版中 Noto CJK、文泉驿等中文字体的位置
+const CJK_FONT_PATHS: &[&str] = &[
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/opentype/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/wenquanyi/wqy-microhei/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-zenhei.ttc",
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+];
+
+impl Platform for UnixPlatform {
+    fn browser_sources(&self) -> Vec<Box<dyn BrowserSource>> {
+        vec![Box::new(xdg::XdgBrowserSource::from_env())]
+    }
+
+    // 安装 .desktop 文件并在 mimeapps.list 中设为默认浏览器
+    fn register(&self) -> std::io::Result<()> {
+        xdg::register(&xdg_dirs()?, &paths::exe_path()?)
+    }
+
+    fn unregister(&self) -> std::io::Result<()> {
+        xdg::unregister(&xdg_dirs()?)
+    }
+
+    fn is_registered(&self) -> bool {
+        xdg_dirs().is_ok_and(|dirs| xdg::is_registered(&dirs))
+    }
+
+    // 只注册到当前用户，不需要提权
+    fn is_elevated(&self) -> bool {
+        true
+    }
+
+    fn run_elevated(&self, _arg: &str) -> bool {
+        false
+    }
+
+    fn font_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = CJK_FONT_PATHS
+            .iter()
+            .map(PathBuf::from)
+            .filter(|path| path.exists())
+            .collect();
+
+        // 以上位置都没有时，由 fontconfig 查找支持中文的字体
+        if paths.is_empty() {
+            if let Ok(output) = Command::new("fc-match")
+                .args(["-f", "%{file}", "sans-serif:lang=zh-cn"])
+                .output()
+            {
+                let file = String::from_utf8_lossy(&output.stdout).trim().to_string();
+                if output.status.success() && !file.is_empty() {
+                    paths.push(PathBuf::from(file));
+                }
+            }
+        }
+        paths
+    }
+
+    // Icon= 可以是绝对路径或主题中的图标名
+    fn load_icon(&self, location: &str) -> Option<egui::ColorImage> {
+        load_image_file(&xdg::resolve_icon(location, &xdg::icon_dirs())?)
+    }
+
+    // Linux 的程序没有版本资源，Firefox 系浏览器在程序旁的 application.ini 中记录名称和版本
+    fn version_info(&self, executable: &Path) -> Option<VersionInfo> {
+        let content = std::fs::read_to_string(executable.parent()?.join("application.ini")).ok()?;
+        Some(VersionInfo {
+            product_name: xdg::ini_get(&content, "App", "Name"),
+            version: xdg::ini_get(&content, "App", "Version"),
+        })
+    }
+
+    fn discovery_stamp(&self) -> Option<String> {
+        Some(xdg::discovery_stamp(&xdg::application_dirs()))
+    }
+
+    fn config_dir(&self) -> Option<PathBuf> {
+        xdg::XdgDirs::from_env().ok().map(|dirs| dirs.config_home)
+    }
+
+    fn policy_source(&self) -> Box<dyn PolicySource> {
+        Box::new(FilePolicySource {
+            path: PathBuf::from(POLICY_FILE),
+        })
+    }
+
+    // 终端中运行时本来就有标准输出
+    fn attach_console(&self) {}
+}
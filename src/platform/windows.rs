@@ -0,0 +1,304 @@
+use super::registry::{self, Registry, RegistryKey};
+use super::{load_image_file, Platform, VersionInfo};
+use crate::policy::{PolicySource, RegistryPolicySource};
+use crate::{paths, Browser, BrowserSource};
+use eframe::egui;
+use is_elevated::is_elevated;
+use std::io;
+use std::path::{Path, PathBuf};
+use windows_sys::Win32::Graphics::Gdi::{
+    CreateCompatibleDC, DeleteDC, DeleteObject, GetDIBits, GetObjectW, BITMAP, BITMAPINFO,
+    BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS, HBITMAP,
+};
+use windows_sys::Win32::System::Console::{
+    AttachConsole, GetStdHandle, ATTACH_PARENT_PROCESS, STD_OUTPUT_HANDLE,
+};
+use windows_sys::Win32::Storage::FileSystem::{
+    GetFileVersionInfoSizeW, GetFileVersionInfoW, VerQueryValueW,
+};
+use windows_sys::Win32::UI::Shell::ExtractIconExW;
+use windows_sys::Win32::UI::WindowsAndMessaging::{DestroyIcon, GetIconInfo, HICON, ICONINFO};
+use winreg::enums::*;
+use winreg::RegKey;
+
+pub struct WindowsPlatform;
+
+// 系统注册表
+struct WinRegistry;
+
+impl Registry for WinRegistry {
+    type Key = RegKey;
+
+    fn local_machine(&self) -> RegKey {
+        RegKey::predef(HKEY_LOCAL_MACHINE)
+    }
+
+    fn current_user(&self) -> RegKey {
+        RegKey::predef(HKEY_CURRENT_USER)
+    }
+}
+
+impl RegistryKey for RegKey {
+    fn open_subkey(&self, path: &str) -> io::Result<Self> {
+        RegKey::open_subkey(self, path)
+    }
+
+    fn open_subkey_writable(&self, path: &str) -> io::Result<Self> {
+        self.open_subkey_with_flags(path, KEY_READ | KEY_WRITE)
+    }
+
+    fn create_subkey(&self, path: &str) -> io::Result<Self> {
+        RegKey::create_subkey(self, path).map(|(key, _)| key)
+    }
+
+    fn enum_keys(&self) -> Vec<String> {
+        RegKey::enum_keys(self).filter_map(Result::ok).collect()
+    }
+
+    fn get_string(&self, name: &str) -> io::Result<String> {
+        self.get_value::<String, _>(name)
+    }
+
+    fn set_string(&self, name: &str, value: &str) -> io::Result<()> {
+        self.set_value(name, &value)
+    }
+
+    fn delete_subkey_all(&self, path: &str) -> io::Result<()> {
+        RegKey::delete_subkey_all(self, path)
+    }
+
+    fn delete_value(&self, name: &str) -> io::Result<()> {
+        RegKey::delete_value(self, name)
+    }
+
+    fn last_write_time(&self) -> Option<u64> {
+        let time = self.query_info().ok()?.last_write_time;
+        Some((u64::from(time.dwHighDateTime) << 32) | u64::from(time.dwLowDateTime))
+    }
+}
+
+// HKLM 和 HKCU 下 StartMenuInternet 中注册的浏览器
+struct RegistryBrowserSource;
+
+impl BrowserSource for RegistryBrowserSource {
+    fn browsers(&self) -> Vec<Browser> {
+        registry::get_installed_browsers(&WinRegistry)
+    }
+}
+
+// 从 exe/dll 资源中提取大图标
+fn extract_icon(path: &str, index: i32) -> Option<egui::ColorImage> {
+    let path = wide(path);
+    let mut icon: HICON = 0;
+    unsafe {
+        if ExtractIconExW(path.as_ptr(), index, &mut icon, std::ptr::null_mut(), 1) == 0 || icon == 0 {
+            return None;
+        }
+        let image = icon_to_image(icon);
+        DestroyIcon(icon);
+        image
+    }
+}
+
+unsafe fn icon_to_image(icon: HICON) -> Option<egui::ColorImage> {
+    let mut info: ICONINFO = std::mem::zeroed();
+    if GetIconInfo(icon, &mut info) == 0 {
+        return None;
+    }
+    // 单色图标没有彩色位图
+    let image = if info.hbmColor != 0 {
+        bitmap_to_image(info.hbmColor)
+    } else {
+        None
+    };
+    DeleteObject(info.hbmColor);
+    DeleteObject(info.hbmMask);
+    image
+}
+
+unsafe fn bitmap_to_image(bitmap: HBITMAP) -> Option<egui::ColorImage> {
+    let mut bm: BITMAP = std::mem::zeroed();
+    if GetObjectW(
+        bitmap,
+        std::mem::size_of::<BITMAP>() as i32,
+        &mut bm as *mut BITMAP as *mut std::ffi::c_void,
+    ) == 0
+    {
+        return None;
+    }
+    let (width, height) = (bm.bmWidth, bm.bmHeight);
+    if width <= 0 || height <= 0 {
+        return None;
+    }
+
+    // 以32位自上而下的格式读取像素
+    let mut info: BITMAPINFO = std::mem::zeroed();
+    info.bmiHeader = BITMAPINFOHEADER {
+        biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+        biWidth: width,
+        biHeight: -height,
+        biPlanes: 1,
+        biBitCount: 32,
+        biCompression: BI_RGB,
+        ..std::mem::zeroed()
+    };
+    let mut pixels = vec![0u8; width as usize * height as usize * 4];
+    let dc = CreateCompatibleDC(0);
+    let lines = GetDIBits(
+        dc,
+        bitmap,
+        0,
+        height as u32,
+        pixels.as_mut_ptr() as *mut std::ffi::c_void,
+        &mut info,
+        DIB_RGB_COLORS,
+    );
+    DeleteDC(dc);
+    if lines == 0 {
+        return None;
+    }
+
+    // BGRA -> RGBA；没有透明通道的旧图标视为完全不透明
+    for pixel in pixels.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+    if pixels.chunks_exact(4).all(|pixel| pixel[3] == 0) {
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel[3] = 255;
+        }
+    }
+    Some(egui::ColorImage::from_rgba_unmultiplied(
+        [width as usize, height as usize],
+        &pixels,
+    ))
+}
+
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+// 在版本资源中查询一项，返回数据指针和长度（字符串为字符数）
+unsafe fn query_version_value(data: &[u8], sub_block: &str) -> Option<(*const std::ffi::c_void, u32)> {
+    let sub_block = wide(sub_block);
+    let mut buffer = std::ptr::null_mut();
+    let mut len = 0u32;
+    if VerQueryValueW(data.as_ptr() as *const std::ffi::c_void, sub_block.as_ptr(), &mut buffer, &mut len) == 0
+        || buffer.is_null()
+        || len == 0
+    {
+        return None;
+    }
+    Some((buffer as *const std::ffi::c_void, len))
+}
+
+unsafe fn query_version_string(data: &[u8], translation: &str, name: &str) -> Option<String> {
+    let (buffer, len) = query_version_value(data, &format!("\\StringFileInfo\\{}\\{}", translation, name))?;
+    let chars = std::slice::from_raw_parts(buffer as *const u16, len as usize);
+    let value = String::from_utf16_lossy(chars).trim_end_matches('\0').trim().to_string();
+    (!value.is_empty()).then_some(value)
+}
+
+// 读取程序版本资源中的 ProductName 和 ProductVersion
+fn read_version_info(path: &Path) -> Option<VersionInfo> {
+    let path = wide(path.to_str()?);
+    unsafe {
+        let size = GetFileVersionInfoSizeW(path.as_ptr(), std::ptr::null_mut());
+        if size == 0 {
+            return None;
+        }
+        let mut data = vec![0u8; size as usize];
+        if GetFileVersionInfoW(path.as_ptr(), 0, size, data.as_mut_ptr() as *mut std::ffi::c_void) == 0 {
+            return None;
+        }
+
+        // 第一个语言和代码页，没有时使用美国英语 + Unicode
+        let translation = match query_version_value(&data, "\\VarFileInfo\\Translation") {
+            Some((buffer, len)) if len >= 4 => {
+                let pair = std::slice::from_raw_parts(buffer as *const u16, 2);
+                format!("{:04x}{:04x}", pair[0], pair[1])
+            }
+            _ => "040904b0".to_string(),
+        };
+        Some(VersionInfo {
+            product_name: query_version_string(&data, &translation, "ProductName"),
+            version: query_version_string(&data, &translation, "ProductVersion"),
+        })
+    }
+}
+
+impl Platform for WindowsPlatform {
+    fn browser_sources(&self) -> Vec<Box<dyn BrowserSource>> {
+        vec![Box::new(RegistryBrowserSource)]
+    }
+
+    fn register(&self) -> io::Result<()> {
+        registry::register_app(&WinRegistry, &paths::exe_path()?)
+    }
+
+    fn unregister(&self) -> io::Result<()> {
+        registry::unregister_app(&WinRegistry)
+    }
+
+    fn is_registered(&self) -> bool {
+        registry::is_registered(&WinRegistry)
+    }
+
+    fn is_elevated(&self) -> bool {
+        is_elevated()
+    }
+
+    fn run_elevated(&self, arg: &str) -> bool {
+        let Ok(exe) = paths::exe_path() else {
+            return false;
+        };
+        matches!(runas::Command::new(exe).arg(arg).status(), Ok(status) if status.success())
+    }
+
+    fn font_paths(&self) -> Vec<PathBuf> {
+        let fonts_dir = std::env::var_os("WINDIR")
+            .map(|dir| PathBuf::from(dir).join("Fonts"))
+            .unwrap_or_else(|| PathBuf::from("C:\\Windows\\Fonts"));
+        ["msyh.ttc", "simhei.ttf"]
+            .iter()
+            .map(|name| fonts_dir.join(name))
+            .collect()
+    }
+
+    // DefaultIcon 形式的位置："路径,索引"，路径可以是 exe、dll 或 ico 文件
+    fn load_icon(&self, location: &str) -> Option<egui::ColorImage> {
+        let (path, index) = registry::parse_icon_location(location);
+        let is_image = Path::new(&path)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("ico") || ext.eq_ignore_ascii_case("png"));
+        if is_image {
+            load_image_file(Path::new(&path))
+        } else {
+            extract_icon(&path, index)
+        }
+    }
+
+    fn version_info(&self, executable: &Path) -> Option<VersionInfo> {
+        read_version_info(executable)
+    }
+
+    fn discovery_stamp(&self) -> Option<String> {
+        Some(registry::discovery_stamp(&WinRegistry))
+    }
+
+    fn config_dir(&self) -> Option<PathBuf> {
+        std::env::var_os("APPDATA").filter(|v| !v.is_empty()).map(PathBuf::from)
+    }
+
+    fn policy_source(&self) -> Box<dyn PolicySource> {
+        Box::new(RegistryPolicySource(WinRegistry))
+    }
+
+    // 程序是 windows 子系统，没有自己的控制台；输出被重定向到文件或管道时已有句柄，不需要附加
+    fn attach_console(&self) {
+        unsafe {
+            if GetStdHandle(STD_OUTPUT_HANDLE) == 0 {
+                AttachConsole(ATTACH_PARENT_PROCESS);
+            }
+        }
+    }
+}
@@ -0,0 +1,865 @@
+use crate::{Browser, BrowserSource};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+// 注册到系统时安装的 .desktop 文件ID
+pub const DESKTOP_ID: &str = "fuckHttp.desktop";
+
+const URL_SCHEMES: &[&str] = &["x-scheme-handler/http", "x-scheme-handler/https"];
+
+// .desktop 文件中 [Desktop Entry] 组的键值
+pub struct DesktopEntry {
+    // 桌面文件ID，子目录中的文件用 - 连接，例如 kde4-konqueror.desktop
+    pub id: String,
+    pub path: PathBuf,
+    fields: HashMap<String, String>,
+}
+
+impl DesktopEntry {
+    pub fn parse(path: &Path, content: &str) -> Option<Self> {
+        let mut fields = HashMap::new();
+        let mut in_main_group = false;
+        let mut found_group = false;
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') {
+                in_main_group = line == "[Desktop Entry]";
+                found_group |= in_main_group;
+                continue;
+            }
+            if !in_main_group {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                fields
+                    .entry(key.trim().to_string())
+                    .or_insert_with(|| unescape_value(value.trim()));
+            }
+        }
+
+        found_group.then(|| Self {
+            id: path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            path: path.to_path_buf(),
+            fields,
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields.get(key).map(String::as_str)
+    }
+
+    pub fn get_bool(&self, key: &str) -> bool {
+        self.get(key) == Some("true")
+    }
+
+    // 按 lang_COUNTRY@MODIFIER、lang_COUNTRY、lang@MODIFIER、lang 的顺序查找本地化值
+    pub fn get_localized(&self, key: &str, locale: Option<&str>) -> Option<&str> {
+        if let Some(locale) = locale {
+            for candidate in locale_candidates(locale) {
+                if let Some(value) = self.get(&format!("{}[{}]", key, candidate)) {
+                    return Some(value);
+                }
+            }
+        }
+        self.get(key)
+    }
+
+    // 以分号分隔的列表值
+    pub fn get_list(&self, key: &str) -> Vec<&str> {
+        self.get(key)
+            .map(|v| v.split(';').map(str::trim).filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn handles_http(&self) -> bool {
+        self.get_list("MimeType")
+            .iter()
+            .any(|m| *m == "x-scheme-handler/http" || *m == "x-scheme-handler/https")
+    }
+}
+
+// 字符串值的转义：\s \n \t \r \\
+fn unescape_value(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => result.push(' '),
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+fn locale_candidates(locale: &str) -> Vec<String> {
+    // 去掉编码部分，例如 zh_CN.UTF-8@latin -> zh_CN@latin
+    let (base, modifier) = match locale.split_once('@') {
+        Some((base, modifier)) => (base, Some(modifier)),
+        None => (locale, None),
+    };
+    let base = base.split('.').next().unwrap_or(base);
+    let (lang, country) = match base.split_once('_') {
+        Some((lang, country)) => (lang, Some(country)),
+        None => (base, None),
+    };
+
+    let mut candidates = Vec::new();
+    if let (Some(country), Some(modifier)) = (country, modifier) {
+        candidates.push(format!("{}_{}@{}", lang, country, modifier));
+    }
+    if let Some(country) = country {
+        candidates.push(format!("{}_{}", lang, country));
+    }
+    if let Some(modifier) = modifier {
+        candidates.push(format!("{}@{}", lang, modifier));
+    }
+    candidates.push(lang.to_string());
+    candidates
+}
+
+// 当前界面语言，取自 LC_ALL、LC_MESSAGES、LANG
+pub fn current_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .filter(|value| value != "C" && value != "POSIX")
+}
+
+// 按 Exec 的引号规则拆分参数：双引号内可用反斜杠转义 " ` $ \
+pub fn parse_exec(exec: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_token = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_token = true;
+            }
+            '\\' if in_quotes => {
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_token {
+                    args.push(std::mem::take(&mut current));
+                    has_token = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_token = true;
+            }
+        }
+    }
+    if has_token {
+        args.push(current);
+    }
+    args
+}
+
+// 把 Exec 转换为命令模板：%u %U %f %F 替换为 {url}，%c 为名称，%k 为文件路径，其余字段代码删除
+pub fn exec_to_command(exec: &str, name: &str, desktop_path: &Path) -> Option<String> {
+    let mut args = Vec::new();
+    let mut has_url = false;
+
+    for arg in parse_exec(exec) {
+        match arg.as_str() {
+            "%u" | "%U" | "%f" | "%F" => {
+                if !has_url {
+                    args.push("{url}".to_string());
+                    has_url = true;
+                }
+            }
+            "%i" | "%d" | "%D" | "%n" | "%N" | "%v" | "%m" => {}
+            _ => {
+                let mut expanded = String::new();
+                let mut chars = arg.chars();
+                while let Some(c) = chars.next() {
+                    if c != '%' {
+                        expanded.push(c);
+                        continue;
+                    }
+                    match chars.next() {
+                        Some('%') => expanded.push('%'),
+                        Some('u' | 'f') if !has_url => {
+                            expanded.push_str("{url}");
+                            has_url = true;
+                        }
+                        Some('c') => expanded.push_str(name),
+                        Some('k') => expanded.push_str(&desktop_path.to_string_lossy()),
+                        _ => {}
+                    }
+                }
+                args.push(expanded);
+            }
+        }
+    }
+
+    if args.is_empty() {
+        return None;
+    }
+    Some(
+        args.iter()
+            .map(|arg| {
+                if arg.chars().any(char::is_whitespace) {
+                    format!("\"{}\"", arg)
+                } else {
+                    arg.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+fn env_path(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+// 按优先级排列的 applications 目录：用户目录、XDG_DATA_DIRS、Flatpak 和 Snap 导出目录
+pub fn application_dirs() -> Vec<PathBuf> {
+    let home = env_path("HOME");
+    let data_home = env_path("XDG_DATA_HOME")
+        .or_else(|| home.as_ref().map(|h| h.join(".local/share")));
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+
+    let mut dirs = Vec::new();
+    if let Some(data_home) = &data_home {
+        dirs.push(data_home.join("applications"));
+        dirs.push(data_home.join("flatpak/exports/share/applications"));
+    }
+    for dir in data_dirs.split(':').filter(|d| !d.is_empty()) {
+        dirs.push(Path::new(dir).join("applications"));
+    }
+    dirs.push(PathBuf::from("/var/lib/flatpak/exports/share/applications"));
+    dirs.push(PathBuf::from("/var/lib/snapd/desktop/applications"));
+
+    let mut seen = HashSet::new();
+    dirs.retain(|d| seen.insert(d.clone()));
+    dirs
+}
+
+// 图标主题的基础目录：~/.icons、各数据目录下的 icons 和 /usr/share/pixmaps
+pub fn icon_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(home) = env_path("HOME") {
+        dirs.push(home.join(".icons"));
+    }
+    for applications in application_dirs() {
+        if let Some(share) = applications.parent() {
+            dirs.push(share.join("icons"));
+        }
+    }
+    dirs.push(PathBuf::from("/usr/share/pixmaps"));
+
+    let mut seen = HashSet::new();
+    dirs.retain(|d| seen.insert(d.clone()));
+    dirs
+}
+
+// 优先使用的 hicolor 图标尺寸
+const ICON_SIZES: &[u32] = &[48, 64, 32, 128, 256, 24, 16];
+
+// 把 Icon= 的值解析为 PNG 文件：先按尺寸查找 hicolor 主题的 apps 图标，再找 /usr/share/pixmaps 等目录下的同名 PNG；
+// 不查找其他图标主题，也不支持 SVG、XPM
+pub fn resolve_icon(icon: &str, dirs: &[PathBuf]) -> Option<PathBuf> {
+    let is_png = |path: &Path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+
+    let path = Path::new(icon);
+    if path.is_absolute() {
+        return (is_png(path) && path.is_file()).then(|| path.to_path_buf());
+    }
+
+    let file_name = format!("{}.png", icon.trim_end_matches(".png"));
+    for size in ICON_SIZES {
+        for dir in dirs {
+            let candidate = dir
+                .join("hicolor")
+                .join(format!("{0}x{0}", size))
+                .join("apps")
+                .join(&file_name);
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+        }
+    }
+    // 没有 hicolor 图标时退回 /usr/share/pixmaps/<名称>.png
+    dirs.iter().map(|dir| dir.join(&file_name)).find(|candidate| candidate.is_file())
+}
+
+// 递归收集 .desktop 文件，子目录中的文件ID用 - 连接
+fn collect_desktop_files(dir: &Path, prefix: &str, files: &mut Vec<(String, PathBuf)>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<_> = entries.filter_map(Result::ok).collect();
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if path.is_dir() {
+            collect_desktop_files(&path, &format!("{}{}-", prefix, file_name), files);
+        } else if file_name.ends_with(".desktop") {
+            files.push((format!("{}{}", prefix, file_name), path));
+        }
+    }
+}
+
+// 目录及其子目录的修改时间，.desktop 文件增删或替换时会变化
+fn collect_dir_mtimes(dir: &Path, times: &mut Vec<String>) {
+    let modified = fs::metadata(dir)
+        .and_then(|m| m.modified())
+        .map(|t| t.duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0));
+    let Ok(modified) = modified else {
+        times.push(format!("{}=-", dir.display()));
+        return;
+    };
+    times.push(format!("{}={}", dir.display(), modified));
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut subdirs: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect();
+    subdirs.sort();
+    for subdir in subdirs {
+        collect_dir_mtimes(&subdir, times);
+    }
+}
+
+// applications 目录的修改时间，用于判断浏览器缓存是否过期
+pub fn discovery_stamp(dirs: &[PathBuf]) -> String {
+    let mut times = Vec::new();
+    for dir in dirs {
+        collect_dir_mtimes(dir, &mut times);
+    }
+    times.join(",")
+}
+
+// 从 XDG .desktop 文件发现浏览器
+pub struct XdgBrowserSource {
+    pub dirs: Vec<PathBuf>,
+    pub locale: Option<String>,
+}
+
+impl XdgBrowserSource {
+    pub fn from_env() -> Self {
+        Self {
+            dirs: application_dirs(),
+            locale: current_locale(),
+        }
+    }
+
+    pub fn entries(&self) -> Vec<DesktopEntry> {
+        let mut seen_ids = HashSet::new();
+        let mut entries = Vec::new();
+        for dir in &self.dirs {
+            let mut files = Vec::new();
+            collect_desktop_files(dir, "", &mut files);
+            for (id, path) in files {
+                // 同一ID以优先级高的目录为准，即使该文件被隐藏
+                if !seen_ids.insert(id.clone()) {
+                    continue;
+                }
+                if let Some(mut entry) = fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|content| DesktopEntry::parse(&path, &content))
+                {
+                    entry.id = id;
+                    entries.push(entry);
+                }
+            }
+        }
+        entries
+    }
+}
+
+impl BrowserSource for XdgBrowserSource {
+    fn browsers(&self) -> Vec<Browser> {
+        self.entries()
+            .into_iter()
+            .filter(|e| e.get("Type").unwrap_or("Application") == "Application")
+            .filter(|e| !e.get_bool("Hidden") && !e.get_bool("NoDisplay"))
+            .filter(|e| e.id != DESKTOP_ID)
+            .filter(DesktopEntry::handles_http)
+            .filter_map(|e| {
+                let name = e.get_localized("Name", self.locale.as_deref())?.to_string();
+                let command = exec_to_command(e.get("Exec")?, &name, &e.path)?;
+                Some(Browser {
+                    id: crate::browser_id(&e.id, &command),
+                    name,
+                    command,
+                    hidden: false,
+                    icon: e.get("Icon").filter(|icon| !icon.is_empty()).map(str::to_string),
+                    broken: None,
+                })
+            })
+            .collect()
+    }
+}
+
+// 注册使用的用户目录
+pub struct XdgDirs {
+    pub config_home: PathBuf,
+    pub data_home: PathBuf,
+    // fuckHttp 自己的状态文件，便携模式下在程序旁边的 data 目录
+    pub state_dir: PathBuf,
+}
+
+impl XdgDirs {
+    pub fn from_env() -> io::Result<Self> {
+        let home = env_path("HOME");
+        let resolve = |name: &str, fallback: &str| {
+            env_path(name)
+                .or_else(|| home.as_ref().map(|h| h.join(fallback)))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "无法确定用户主目录"))
+        };
+        let data_home = resolve("XDG_DATA_HOME", ".local/share")?;
+        Ok(Self {
+            config_home: resolve("XDG_CONFIG_HOME", ".config")?,
+            state_dir: data_home.join("fuckHttp"),
+            data_home,
+        })
+    }
+
+    fn desktop_file(&self) -> PathBuf {
+        self.data_home.join("applications").join(DESKTOP_ID)
+    }
+
+    fn mimeapps_list(&self) -> PathBuf {
+        self.config_home.join("mimeapps.list")
+    }
+
+    // 注册前的默认浏览器，卸载时恢复
+    fn previous_defaults(&self) -> PathBuf {
+        self.state_dir.join("previous_defaults.json")
+    }
+}
+
+// Exec 中的参数加引号，并按字符串值的规则转义反斜杠
+fn quote_exec_arg(arg: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        match c {
+            '"' | '`' | '$' => {
+                quoted.push_str("\\\\");
+                quoted.push(c);
+            }
+            '\\' => quoted.push_str("\\\\\\\\"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn desktop_file_content(exe: &Path) -> String {
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=fuckHttp\n\
+         Comment=A custom browser selector.\n\
+         Exec={} %u\n\
+         Terminal=false\n\
+         Categories=Network;WebBrowser;\n\
+         MimeType=x-scheme-handler/http;x-scheme-handler/https;\n",
+        quote_exec_arg(&exe.to_string_lossy())
+    )
+}
+
+// 读取 ini 格式内容中指定组的键值
+pub(super) fn ini_get(content: &str, group: &str, key: &str) -> Option<String> {
+    let header = format!("[{}]", group);
+    let mut in_group = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_group = line == header;
+        } else if in_group {
+            if let Some((k, v)) = line.split_once('=') {
+                if k.trim() == key {
+                    return Some(v.trim().to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
+// 设置或删除指定组的键值，保留文件中的其它内容
+fn ini_set(content: &str, group: &str, key: &str, value: Option<&str>) -> String {
+    let header = format!("[{}]", group);
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let group_start = lines.iter().position(|l| l.trim() == header);
+
+    let Some(group_start) = group_start else {
+        if let Some(value) = value {
+            if lines.last().map(|l| !l.trim().is_empty()).unwrap_or(false) {
+                lines.push(String::new());
+            }
+            lines.push(header);
+            lines.push(format!("{}={}", key, value));
+        }
+        return lines.join("\n") + "\n";
+    };
+
+    let group_end = lines[group_start + 1..]
+        .iter()
+        .position(|l| l.trim().starts_with('['))
+        .map(|i| group_start + 1 + i)
+        .unwrap_or(lines.len());
+    let existing = (group_start + 1..group_end).find(|&i| {
+        lines[i]
+            .split_once('=')
+            .map(|(k, _)| k.trim() == key)
+            .unwrap_or(false)
+    });
+
+    match (existing, value) {
+        (Some(i), Some(value)) => lines[i] = format!("{}={}", key, value),
+        (Some(i), None) => {
+            lines.remove(i);
+        }
+        (None, Some(value)) => {
+            // 插入到组内最后一个非空行之后
+            let mut insert_at = group_end;
+            while insert_at > group_start + 1 && lines[insert_at - 1].trim().is_empty() {
+                insert_at -= 1;
+            }
+            lines.insert(insert_at, format!("{}={}", key, value));
+        }
+        (None, None) => {}
+    }
+    lines.join("\n") + "\n"
+}
+
+fn read_optional(path: &Path) -> io::Result<String> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e),
+    }
+}
+
+fn write_creating_dirs(path: &Path, content: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)
+}
+
+// 安装 fuckHttp.desktop 并在 mimeapps.list 中设为 http/https 的默认程序，记录原默认程序
+pub fn register(dirs: &XdgDirs, exe: &Path) -> io::Result<()> {
+    write_creating_dirs(&dirs.desktop_file(), &desktop_file_content(exe))?;
+
+    let mimeapps_path = dirs.mimeapps_list();
+    let mut mimeapps = read_optional(&mimeapps_path)?;
+
+    // 已注册时保留第一次注册前的记录
+    let previous_path = dirs.previous_defaults();
+    if !previous_path.exists() {
+        let previous: HashMap<&str, String> = URL_SCHEMES
+            .iter()
+            .filter_map(|scheme| {
+                ini_get(&mimeapps, "Default Applications", scheme)
+                    .filter(|v| v != DESKTOP_ID)
+                    .map(|v| (*scheme, v))
+            })
+            .collect();
+        let content = serde_json::to_string_pretty(&previous)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        write_creating_dirs(&previous_path, &content)?;
+    }
+
+    for scheme in URL_SCHEMES {
+        mimeapps = ini_set(&mimeapps, "Default Applications", scheme, Some(DESKTOP_ID));
+    }
+    write_creating_dirs(&mimeapps_path, &mimeapps)
+}
+
+// 是否已注册：fuckHttp.desktop 存在且是 http/https 的默认程序
+pub fn is_registered(dirs: &XdgDirs) -> bool {
+    let mimeapps = read_optional(&dirs.mimeapps_list()).unwrap_or_default();
+    dirs.desktop_file().is_file()
+        && URL_SCHEMES
+            .iter()
+            .all(|scheme| ini_get(&mimeapps, "Default Applications", scheme).as_deref() == Some(DESKTOP_ID))
+}
+
+// 删除 fuckHttp.desktop，并把 mimeapps.list 中的默认程序恢复为注册前的值
+pub fn unregister(dirs: &XdgDirs) -> io::Result<()> {
+    let previous_path = dirs.previous_defaults();
+    let previous: HashMap<String, String> = read_optional(&previous_path)?
+        .parse::<serde_json::Value>()
+        .ok()
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+
+    let mimeapps_path = dirs.mimeapps_list();
+    let mut mimeapps = read_optional(&mimeapps_path)?;
+    for scheme in URL_SCHEMES {
+        // 注册后用户已改用其它浏览器时不做改动
+        if ini_get(&mimeapps, "Default Applications", scheme).as_deref() == Some(DESKTOP_ID) {
+            let restored = previous.get(*scheme).map(String::as_str);
+            mimeapps = ini_set(&mimeapps, "Default Applications", scheme, restored);
+        }
+    }
+    if !mimeapps.trim().is_empty() || mimeapps_path.exists() {
+        write_creating_dirs(&mimeapps_path, &mimeapps)?;
+    }
+
+    for path in [dirs.desktop_file(), previous_path] {
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIREFOX: &str = "[Desktop Entry]
+Version=1.0
+Name=Firefox Web Browser
+Name[zh_CN]=Firefox 网络浏览器
+Exec=firefox %u
+Type=Application
+MimeType=text/html;x-scheme-handler/http;x-scheme-handler/https;
+
+[Desktop Action new-window]
+Name=Open a New Window
+Exec=firefox --new-window %u
+";
+
+    #[test]
+    fn parses_main_group_only() {
+        let entry = DesktopEntry::parse(Path::new("/x"), FIREFOX).unwrap();
+        assert_eq!(entry.get("Exec"), Some("firefox %u"));
+        assert!(entry.handles_http());
+        assert_eq!(
+            entry.get_localized("Name", Some("zh_CN.UTF-8")),
+            Some("Firefox 网络浏览器")
+        );
+        assert_eq!(entry.get_localized("Name", Some("de_DE")), Some("Firefox Web Browser"));
+        assert_eq!(entry.get_localized("Name", None), Some("Firefox Web Browser"));
+    }
+
+    #[test]
+    fn converts_exec_field_codes() {
+        let path = Path::new("/usr/share/applications/app.desktop");
+        assert_eq!(
+            exec_to_command("/usr/bin/google-chrome-stable %U", "Chrome", path).as_deref(),
+            Some("/usr/bin/google-chrome-stable {url}")
+        );
+        assert_eq!(
+            exec_to_command(
+                "/usr/bin/flatpak run --branch=stable --command=firefox org.mozilla.firefox @@u %u @@",
+                "Firefox",
+                path
+            )
+            .as_deref(),
+            Some("/usr/bin/flatpak run --branch=stable --command=firefox org.mozilla.firefox @@u {url} @@")
+        );
+        assert_eq!(
+            exec_to_command("\"/opt/My Browser/browser\" --name=%c %i %f %F", "My Browser", path).as_deref(),
+            Some("\"/opt/My Browser/browser\" \"--name=My Browser\" {url}")
+        );
+        assert_eq!(
+            exec_to_command("env 100%% browser", "B", path).as_deref(),
+            Some("env 100% browser")
+        );
+    }
+
+    #[test]
+    fn scans_dirs_with_precedence() {
+        let user = tempfile::tempdir().unwrap();
+        let system = tempfile::tempdir().unwrap();
+        fs::create_dir(system.path().join("vendor")).unwrap();
+
+        // 用户目录中的同名文件覆盖系统目录，并且可以隐藏系统条目
+        fs::write(user.path().join("firefox.desktop"), FIREFOX.replace("firefox %u", "firefox-user %u")).unwrap();
+        fs::write(user.path().join("chromium.desktop"), "[Desktop Entry]\nName=Chromium\nExec=chromium\nNoDisplay=true\nMimeType=x-scheme-handler/https;\n").unwrap();
+        fs::write(system.path().join("firefox.desktop"), FIREFOX).unwrap();
+        fs::write(system.path().join("chromium.desktop"), "[Desktop Entry]\nName=Chromium\nExec=chromium %U\nMimeType=x-scheme-handler/https;\n").unwrap();
+        fs::write(system.path().join("vendor/edge.desktop"), "[Desktop Entry]\nName=Edge\nExec=microsoft-edge %U\nMimeType=x-scheme-handler/http;\n").unwrap();
+        fs::write(system.path().join("editor.desktop"), "[Desktop Entry]\nName=Editor\nExec=gedit %F\nMimeType=text/plain;\n").unwrap();
+
+        let source = XdgBrowserSource {
+            dirs: vec![user.path().to_path_buf(), system.path().to_path_buf()],
+            locale: Some("zh_CN.UTF-8".to_string()),
+        };
+        let browsers = source.browsers();
+        let names: Vec<_> = browsers.iter().map(|b| (b.name.as_str(), b.command.as_str())).collect();
+        assert_eq!(
+            names,
+            vec![
+                ("Firefox 网络浏览器", "firefox-user {url}"),
+                ("Edge", "microsoft-edge {url}"),
+            ]
+        );
+        assert_eq!(browsers[1].id, "vendor-edge.desktop|microsoft-edge");
+    }
+
+    #[test]
+    fn resolves_icons_from_hicolor_theme() {
+        let user = tempfile::tempdir().unwrap();
+        let system = tempfile::tempdir().unwrap();
+        let pixmaps = tempfile::tempdir().unwrap();
+        let dirs = vec![
+            user.path().to_path_buf(),
+            system.path().to_path_buf(),
+            pixmaps.path().to_path_buf(),
+        ];
+
+        for (dir, size) in [(system.path(), "128x128"), (system.path(), "48x48"), (user.path(), "256x256")] {
+            let apps = dir.join("hicolor").join(size).join("apps");
+            fs::create_dir_all(&apps).unwrap();
+            fs::write(apps.join("firefox.png"), b"").unwrap();
+        }
+        fs::create_dir_all(system.path().join("hicolor/scalable/apps")).unwrap();
+        fs::write(system.path().join("hicolor/scalable/apps/chromium.svg"), b"").unwrap();
+        fs::write(pixmaps.path().join("opera.png"), b"").unwrap();
+        fs::write(pixmaps.path().join("firefox.png"), b"").unwrap();
+        fs::write(pixmaps.path().join("chromium.xpm"), b"").unwrap();
+
+        // 首选尺寸优先于目录顺序，hicolor 主题优先于 pixmaps
+        assert_eq!(
+            resolve_icon("firefox", &dirs),
+            Some(system.path().join("hicolor/48x48/apps/firefox.png"))
+        );
+        assert_eq!(resolve_icon("opera", &dirs), Some(pixmaps.path().join("opera.png")));
+        assert_eq!(resolve_icon("chromium", &dirs), None);
+
+        let absolute = pixmaps.path().join("opera.png");
+        assert_eq!(resolve_icon(absolute.to_str().unwrap(), &[]), Some(absolute.clone()));
+        assert_eq!(resolve_icon("/nonexistent/icon.png", &dirs), None);
+    }
+
+    #[test]
+    fn discovery_stamp_tracks_application_dirs() {
+        let root = tempfile::tempdir().unwrap();
+        let applications = root.path().join("applications");
+        fs::create_dir(&applications).unwrap();
+        let dirs = vec![applications.clone(), root.path().join("missing")];
+
+        let stamp = discovery_stamp(&dirs);
+        assert_eq!(discovery_stamp(&dirs), stamp);
+        assert!(stamp.ends_with("missing=-"));
+
+        fs::create_dir(applications.join("vendor")).unwrap();
+        assert_ne!(discovery_stamp(&dirs), stamp);
+    }
+
+    fn temp_dirs() -> (tempfile::TempDir, XdgDirs) {
+        let root = tempfile::tempdir().unwrap();
+        let dirs = XdgDirs {
+            config_home: root.path().join("config"),
+            data_home: root.path().join("data"),
+            state_dir: root.path().join("data/fuckHttp"),
+        };
+        (root, dirs)
+    }
+
+    #[test]
+    fn register_and_unregister_restore_previous_default() {
+        let (_root, dirs) = temp_dirs();
+        fs::create_dir_all(&dirs.config_home).unwrap();
+        let original = "[Default Applications]\n\
+                        x-scheme-handler/http=firefox.desktop\n\
+                        x-scheme-handler/https=firefox.desktop\n\
+                        text/html=firefox.desktop\n\
+                        \n\
+                        [Added Associations]\n\
+                        image/png=org.gnome.eog.desktop;\n";
+        fs::write(dirs.mimeapps_list(), original).unwrap();
+
+        register(&dirs, Path::new("/opt/fuck Http/fuckHttp")).unwrap();
+        let mimeapps = fs::read_to_string(dirs.mimeapps_list()).unwrap();
+        assert_eq!(ini_get(&mimeapps, "Default Applications", "x-scheme-handler/http").as_deref(), Some(DESKTOP_ID));
+        assert_eq!(ini_get(&mimeapps, "Default Applications", "x-scheme-handler/https").as_deref(), Some(DESKTOP_ID));
+        assert_eq!(ini_get(&mimeapps, "Default Applications", "text/html").as_deref(), Some("firefox.desktop"));
+
+        let desktop = fs::read_to_string(dirs.desktop_file()).unwrap();
+        let entry = DesktopEntry::parse(&dirs.desktop_file(), &desktop).unwrap();
+        assert!(entry.handles_http());
+        assert_eq!(parse_exec(entry.get("Exec").unwrap()), vec!["/opt/fuck Http/fuckHttp", "%u"]);
+
+        // 重复注册不覆盖原默认程序的记录
+        register(&dirs, Path::new("/opt/fuck Http/fuckHttp")).unwrap();
+
+        unregister(&dirs).unwrap();
+        assert_eq!(fs::read_to_string(dirs.mimeapps_list()).unwrap(), original);
+        assert!(!dirs.desktop_file().exists());
+        assert!(!dirs.previous_defaults().exists());
+    }
+
+    #[test]
+    fn register_without_existing_mimeapps() {
+        let (_root, dirs) = temp_dirs();
+
+        assert!(!is_registered(&dirs));
+        register(&dirs, Path::new("/usr/bin/fuckHttp")).unwrap();
+        assert!(is_registered(&dirs));
+        assert_eq!(
+            fs::read_to_string(dirs.mimeapps_list()).unwrap(),
+            "[Default Applications]\nx-scheme-handler/http=fuckHttp.desktop\nx-scheme-handler/https=fuckHttp.desktop\n"
+        );
+
+        unregister(&dirs).unwrap();
+        assert!(!is_registered(&dirs));
+        assert_eq!(fs::read_to_string(dirs.mimeapps_list()).unwrap(), "[Default Applications]\n");
+    }
+
+    #[test]
+    fn unregister_keeps_default_changed_by_user() {
+        let (_root, dirs) = temp_dirs();
+        register(&dirs, Path::new("/usr/bin/fuckHttp")).unwrap();
+
+        let mimeapps = fs::read_to_string(dirs.mimeapps_list()).unwrap();
+        let changed = ini_set(&mimeapps, "Default Applications", "x-scheme-handler/https", Some("chromium.desktop"));
+        fs::write(dirs.mimeapps_list(), &changed).unwrap();
+
+        unregister(&dirs).unwrap();
+        let mimeapps = fs::read_to_string(dirs.mimeapps_list()).unwrap();
+        assert_eq!(ini_get(&mimeapps, "Default Applications", "x-scheme-handler/http"), None);
+        assert_eq!(
+            ini_get(&mimeapps, "Default Applications", "x-scheme-handler/https").as_deref(),
+            Some("chromium.desktop")
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use crate::actions::RoutingRule;
+use crate::platform::registry::{Registry, RegistryKey};
+use crate::Browser;
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// 组策略在注册表中的位置（HKLM）
+#[cfg_attr(not(windows), allow(dead_code))]
+const POLICY_KEY: &str = "Software\\Policies\\fuckHttp";
+// Linux 上的策略文件
+#[cfg_attr(not(unix), allow(dead_code))]
+pub const POLICY_FILE: &str = "/etc/fuckHttp/policy.json";
+
+// 管理员强制的设置，优先于用户配置和团队预设
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct Policy {
+    // 强制的路由规则，排在用户的规则之前
+    #[serde(default)]
+    pub rules: Vec<RoutingRule>,
+    // 禁止使用的浏览器，可以写浏览器ID或名称
+    #[serde(default)]
+    pub blocked_browsers: Vec<String>,
+    // 禁止（true）或强制允许（false）访问网络提取链接；None 时由用户决定
+    #[serde(default)]
+    pub disable_network_extraction: Option<bool>,
+}
+
+impl Policy {
+    pub fn blocks(&self, browser: &Browser) -> bool {
+        self.blocked_browsers
+            .iter()
+            .any(|blocked| blocked.eq_ignore_ascii_case(&browser.id) || *blocked == browser.name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Policy::default()
+    }
+}
+
+// 策略的来源：Windows 组策略注册表项、Linux 策略文件
+pub trait PolicySource {
+    // 没有配置策略时返回默认值；策略存在但无法读取时返回错误
+    fn policy(&self) -> Result<Policy, String>;
+
+    // 策略文件的位置，用于发现管理员的修改；不是来自文件时为 None
+    fn path(&self) -> Option<&Path> {
+        None
+    }
+}
+
+// HKLM\Software\Policies\fuckHttp 下的值：
+// DisableNetworkExtraction 为 "1"/"0"，BlockedBrowsers 为分号分隔的列表，Rules 为路由规则的 JSON 数组
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct RegistryPolicySource<R: Registry>(pub R);
+
+impl<R: Registry> PolicySource for RegistryPolicySource<R> {
+    fn policy(&self) -> Result<Policy, String> {
+        let key = match self.0.local_machine().open_subkey(POLICY_KEY) {
+            Ok(key) => key,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Policy::default()),
+            Err(e) => return Err(format!("无法读取组策略: {}", e)),
+        };
+        let value = |name: &str| key.get_string(name).ok().filter(|v| !v.trim().is_empty());
+
+        let mut policy = Policy::default();
+        if let Some(flag) = value("DisableNetworkExtraction") {
+            policy.disable_network_extraction = match flag.trim() {
+                "1" | "true" => Some(true),
+                "0" | "false" => Some(false),
+                other => return Err(format!("组策略 DisableNetworkExtraction 的值无效: {}", other)),
+            };
+        }
+        if let Some(blocked) = value("BlockedBrowsers") {
+            policy.blocked_browsers = blocked
+                .split(';')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        if let Some(rules) = value("Rules") {
+            policy.rules = serde_json::from_str(&rules).map_err(|e| format!("组策略 Rules 无效: {}", e))?;
+        }
+        Ok(policy)
+    }
+}
+
+// JSON 格式的策略文件，字段与 Policy 相同
+#[cfg_attr(not(unix), allow(dead_code))]
+pub struct FilePolicySource {
+    pub path: PathBuf,
+}
+
+impl PolicySource for FilePolicySource {
+    fn policy(&self) -> Result<Policy, String> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Policy::default()),
+            Err(e) => return Err(format!("无法读取策略文件 {}: {}", self.path.display(), e)),
+        };
+        serde_json::from_str(&content).map_err(|e| format!("策略文件 {} 无效: {}", self.path.display(), e))
+    }
+
+    fn path(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+}
+
+// 测试用的策略来源
+#[cfg(test)]
+pub mod fake {
+    use super::{Policy, PolicySource};
+
+    pub struct FakePolicySource(pub Result<Policy, String>);
+
+    impl PolicySource for FakePolicySource {
+        fn policy(&self) -> Result<Policy, String> {
+            self.0.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fake::FakePolicySource;
+    use super::*;
+    use crate::actions::RouteTarget;
+    use crate::platform::registry::fake::FakeRegistry;
+
+    #[test]
+    fn reads_group_policy_values() {
+        let registry = FakeRegistry::new();
+        assert_eq!(RegistryPolicySource(FakeRegistry::new()).policy(), Ok(Policy::default()));
+
+        let path = "HKEY_LOCAL_MACHINE\\Software\\Policies\\fuckHttp";
+        registry.set(path, "DisableNetworkExtraction", "1");
+        registry.set(path, "BlockedBrowsers", "iexplore|c:\\ie.exe; Opera ;");
+        registry.set(
+            path,
+            "Rules",
+            r#"[{ "match": { "hosts": ["*.corp.example.com"] }, "target": { "browser": "Microsoft Edge" } }]"#,
+        );
+        let policy = RegistryPolicySource(registry).policy().unwrap();
+        assert_eq!(policy.disable_network_extraction, Some(true));
+        assert_eq!(policy.blocked_browsers, vec!["iexplore|c:\\ie.exe", "Opera"]);
+        assert_eq!(policy.rules[0].target, RouteTarget::Browser("Microsoft Edge".to_string()));
+        // "*." 开头的域名匹配子域名
+        assert!(policy.rules[0].matches.matches("https://wiki.corp.example.com/page"));
+        assert!(!policy.rules[0].matches.matches("https://corp.example.com/"));
+
+        let invalid = FakeRegistry::new();
+        invalid.set(path, "DisableNetworkExtraction", "maybe");
+        assert!(RegistryPolicySource(invalid).policy().is_err());
+    }
+
+    #[test]
+    fn reads_policy_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = FilePolicySource {
+            path: dir.path().join("policy.json"),
+        };
+        assert_eq!(source.policy(), Ok(Policy::default()));
+
+        fs::write(&source.path, r#"{ "blocked_browsers": ["Opera"], "disable_network_extraction": false }"#).unwrap();
+        let policy = source.policy().unwrap();
+        assert_eq!(policy.disable_network_extraction, Some(false));
+        assert!(policy.blocks(&Browser::new("opera.desktop|opera", "Opera")));
+
+        fs::write(&source.path, "{").unwrap();
+        assert!(source.policy().is_err());
+    }
+
+    #[test]
+    fn policy_overrides_user_settings() {
+        let source = FakePolicySource(Ok(Policy {
+            blocked_browsers: vec!["Opera".to_string()],
+            disable_network_extraction: Some(true),
+            ..Policy::default()
+        }));
+        let mut config = crate::Config::default();
+        crate::apply_policy(&mut config, &source);
+
+        assert!(!config.network_extraction_allowed());
+        assert!(!config.shows(&Browser::new("opera.desktop|opera", "Opera")));
+        assert!(config.shows(&Browser::new("firefox.desktop|firefox", "Firefox")));
+
+        // 策略无法读取时记录错误，用户设置照常生效
+        let mut config = crate::Config::default();
+        crate::apply_policy(&mut config, &FakePolicySource(Err("无法读取".to_string())));
+        assert!(config.network_extraction_allowed());
+        assert_eq!(config.policy_error.as_deref(), Some("无法读取"));
+    }
+}
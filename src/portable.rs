@@ -0,0 +1,174 @@
+use crate::platform::VersionInfo;
+use crate::{Browser, BrowserSource};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// 便携版浏览器的程序名和默认显示名称
+#[cfg(windows)]
+const KNOWN_EXECUTABLES: &[(&str, &str)] = &[
+    ("chrome.exe", "Google Chrome"),
+    ("firefox.exe", "Firefox"),
+    ("msedge.exe", "Microsoft Edge"),
+    ("brave.exe", "Brave"),
+    ("chromium.exe", "Chromium"),
+    ("vivaldi.exe", "Vivaldi"),
+    ("opera.exe", "Opera"),
+];
+#[cfg(not(windows))]
+const KNOWN_EXECUTABLES: &[(&str, &str)] = &[
+    ("chrome", "Google Chrome"),
+    ("google-chrome", "Google Chrome"),
+    ("firefox", "Firefox"),
+    ("msedge", "Microsoft Edge"),
+    ("microsoft-edge", "Microsoft Edge"),
+    ("brave", "Brave"),
+    ("brave-browser", "Brave"),
+    ("chromium", "Chromium"),
+    ("chromium-browser", "Chromium"),
+    ("vivaldi", "Vivaldi"),
+    ("opera", "Opera"),
+];
+
+// 便携版的程序通常在 App\Chrome-bin 之类的子目录中，限制递归深度避免扫描整个共享盘
+const MAX_DEPTH: usize = 4;
+
+// 程序旁边的图标文件（Linux 版没有可提取图标的资源）
+#[cfg(not(windows))]
+const ICON_FILES: &[&str] = &[
+    "product_logo_128.png",
+    "product_logo_64.png",
+    "browser/chrome/icons/default/default128.png",
+    "browser/chrome/icons/default/default64.png",
+];
+
+fn known_name(file_name: &str) -> Option<&'static str> {
+    KNOWN_EXECUTABLES
+        .iter()
+        .find(|(exe, _)| {
+            if cfg!(windows) {
+                exe.eq_ignore_ascii_case(file_name)
+            } else {
+                *exe == file_name
+            }
+        })
+        .map(|(_, name)| *name)
+}
+
+fn collect_executables(dir: &Path, depth: usize, found: &mut Vec<(PathBuf, &'static str)>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<_> = entries.filter_map(Result::ok).collect();
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            if depth < MAX_DEPTH {
+                collect_executables(&path, depth + 1, found);
+            }
+        } else if let Some(name) = entry.file_name().to_str().and_then(known_name) {
+            found.push((path, name));
+        }
+    }
+}
+
+#[cfg(windows)]
+fn icon_for(executable: &Path) -> Option<String> {
+    Some(format!("\"{}\",0", executable.display()))
+}
+
+#[cfg(not(windows))]
+fn icon_for(executable: &Path) -> Option<String> {
+    let dir = executable.parent()?;
+    ICON_FILES
+        .iter()
+        .map(|file| dir.join(file))
+        .find(|path| path.is_file())
+        .map(|path| path.display().to_string())
+}
+
+// 扫描目录中的已知浏览器程序，用版本信息中的产品名称和版本号命名
+pub fn scan(dirs: &[PathBuf], version_of: impl Fn(&Path) -> Option<VersionInfo>) -> Vec<Browser> {
+    let mut found = Vec::new();
+    for dir in dirs {
+        collect_executables(dir, 0, &mut found);
+    }
+
+    found
+        .into_iter()
+        .map(|(path, default_name)| {
+            let version = version_of(&path).unwrap_or_default();
+            let product = version.product_name.unwrap_or_else(|| default_name.to_string());
+            let name = match version.version {
+                Some(version) => format!("{} {} (便携版)", product, version),
+                None => format!("{} (便携版)", product),
+            };
+            let command = format!("\"{}\"", path.display());
+            let mut id = crate::browser_id("portable", &command);
+            if cfg!(windows) {
+                id = id.to_lowercase();
+            }
+            Browser {
+                id,
+                name,
+                icon: icon_for(&path),
+                command,
+                hidden: false,
+                broken: None,
+            }
+        })
+        .collect()
+}
+
+// 配置的搜索目录中的便携版浏览器
+pub struct PortableBrowserSource {
+    pub dirs: Vec<PathBuf>,
+}
+
+impl BrowserSource for PortableBrowserSource {
+    fn browsers(&self) -> Vec<Browser> {
+        let platform = crate::platform::current();
+        scan(&self.dirs, |path| platform.version_info(path))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_known_executables_in_nested_dirs() {
+        let root = tempfile::tempdir().unwrap();
+        let chrome_dir = root.path().join("ChromePortable/App/Chrome-bin");
+        let firefox_dir = root.path().join("FirefoxPortable/App/Firefox64");
+        let too_deep = root.path().join("a/b/c/d/e");
+        for dir in [&chrome_dir, &firefox_dir, &too_deep] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        fs::write(chrome_dir.join("chrome"), "").unwrap();
+        fs::write(chrome_dir.join("product_logo_128.png"), "").unwrap();
+        fs::write(firefox_dir.join("firefox"), "").unwrap();
+        fs::write(firefox_dir.join("updater"), "").unwrap();
+        fs::write(too_deep.join("brave"), "").unwrap();
+
+        let browsers = scan(&[root.path().to_path_buf()], |path| {
+            (path.file_name()? == "firefox").then(|| VersionInfo {
+                product_name: Some("Firefox Developer Edition".to_string()),
+                version: Some("128.0".to_string()),
+            })
+        });
+
+        let found: Vec<_> = browsers.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(found, vec!["Google Chrome (便携版)", "Firefox Developer Edition 128.0 (便携版)"]);
+        assert_eq!(browsers[0].command, format!("\"{}\"", chrome_dir.join("chrome").display()));
+        assert_eq!(browsers[0].id, format!("portable|{}", chrome_dir.join("chrome").display()));
+        assert_eq!(
+            browsers[0].icon.as_deref(),
+            Some(chrome_dir.join("product_logo_128.png").to_str().unwrap())
+        );
+        assert_eq!(browsers[1].icon, None);
+    }
+}
@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+// HEAD 请求得到的响应头信息，用于判断链接是否指向文件或媒体
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HeadInfo {
+    pub content_type: Option<String>,
+    pub content_disposition: Option<String>,
+}
+
+impl HeadInfo {
+    // 不含参数的小写MIME类型，例如 "video/mp4"
+    pub fn mime_type(&self) -> Option<String> {
+        self.content_type
+            .as_deref()
+            .and_then(|ct| ct.split(';').next())
+            .map(|mime| mime.trim().to_ascii_lowercase())
+            .filter(|mime| !mime.is_empty())
+    }
+
+    pub fn is_attachment(&self) -> bool {
+        self.content_disposition
+            .as_deref()
+            .map(|cd| cd.to_ascii_lowercase().contains("attachment"))
+            .unwrap_or(false)
+    }
+}
+
+// aria2 和媒体播放器的 probe 选项默认开启，两者共用同一次HEAD请求；禁用联网提取时都不发送
+pub fn enabled_by_default() -> bool {
+    true
+}
+
+// 发送HEAD请求获取响应头，失败时返回None
+pub fn head(url: &str) -> Option<HeadInfo> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .ok()?;
+    let response = client.head(url).send().ok()?;
+    let header = |name: reqwest::header::HeaderName| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+
+    Some(HeadInfo {
+        content_type: header(reqwest::header::CONTENT_TYPE),
+        content_disposition: header(reqwest::header::CONTENT_DISPOSITION),
+    })
+}
@@ -0,0 +1,128 @@
+use serde_json::{Map, Value};
+
+// 当前的配置格式版本；修改格式时加 1，并在 MIGRATIONS 末尾追加对应的迁移函数
+pub const CURRENT_VERSION: u64 = 1;
+
+type Migration = fn(&mut Map<String, Value>);
+
+// MIGRATIONS[n] 把版本 n 的配置升级到版本 n + 1
+const MIGRATIONS: &[Migration] = &[v0_to_v1];
+
+// 版本 0 是没有 version 字段的旧配置：补上当时必填的 hidden_browsers，去掉手工编辑留下的 null
+fn v0_to_v1(config: &mut Map<String, Value>) {
+    config.retain(|_, value| !value.is_null());
+    config
+        .entry("hidden_browsers")
+        .or_insert_with(|| Value::Array(Vec::new()));
+}
+
+// 把配置升级到当前版本并写入 version 字段，返回原来的版本
+pub fn upgrade(value: &mut Value) -> Result<u64, String> {
+    let config = value
+        .as_object_mut()
+        .ok_or_else(|| "配置文件的内容必须是 JSON 对象".to_string())?;
+    let version = match config.get("version") {
+        None => 0,
+        Some(v) => v.as_u64().ok_or_else(|| format!("无效的配置版本: {}", v))?,
+    };
+    if version > CURRENT_VERSION {
+        return Err(format!(
+            "配置文件版本 {} 比当前程序支持的版本 {} 新，请升级 fuckHttp",
+            version, CURRENT_VERSION
+        ));
+    }
+
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(config);
+    }
+    config.insert("version".to_string(), Value::from(CURRENT_VERSION));
+    Ok(version)
+}
+
+// 升级后的配置中有、按当前格式解析再序列化后没有的键，即拼错或已不支持的配置项。
+// 序列化时省略的空值（null、[]、{}）不算；返回带路径的键名，例如 "rules[0].match.patern"
+pub fn unknown_keys(input: &Value, parsed: &Value) -> Vec<String> {
+    let mut keys = Vec::new();
+    collect_unknown_keys(input, parsed, "", &mut keys);
+    keys
+}
+
+fn collect_unknown_keys(input: &Value, parsed: &Value, path: &str, keys: &mut Vec<String>) {
+    match (input, parsed) {
+        (Value::Object(input), Value::Object(parsed)) => {
+            for (key, value) in input {
+                let child = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                match parsed.get(key) {
+                    Some(parsed_value) => collect_unknown_keys(value, parsed_value, &child, keys),
+                    None if is_empty_value(value) => {}
+                    None => keys.push(child),
+                }
+            }
+        }
+        (Value::Array(input), Value::Array(parsed)) if input.len() == parsed.len() => {
+            for (index, (value, parsed_value)) in input.iter().zip(parsed).enumerate() {
+                collect_unknown_keys(value, parsed_value, &format!("{}[{}]", path, index), keys);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_empty_value(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Array(items) => items.is_empty(),
+        Value::Object(map) => map.is_empty(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(content: &str) -> Value {
+        serde_json::from_str(content).unwrap()
+    }
+
+    #[test]
+    fn every_version_has_a_migration() {
+        assert_eq!(MIGRATIONS.len() as u64, CURRENT_VERSION);
+    }
+
+    #[test]
+    fn upgrades_v0_configs() {
+        let mut minimal = fixture(include_str!("../tests/fixtures/config/v0_minimal.json"));
+        assert_eq!(upgrade(&mut minimal), Ok(0));
+        assert_eq!(minimal, fixture(include_str!("../tests/fixtures/config/v0_minimal.v1.json")));
+
+        let mut hand_edited = fixture(include_str!("../tests/fixtures/config/v0_hand_edited.json"));
+        assert_eq!(upgrade(&mut hand_edited), Ok(0));
+        assert_eq!(hand_edited, fixture(include_str!("../tests/fixtures/config/v0_hand_edited.v1.json")));
+    }
+
+    #[test]
+    fn keeps_current_version_unchanged() {
+        let expected = fixture(include_str!("../tests/fixtures/config/v0_hand_edited.v1.json"));
+        let mut config = expected.clone();
+        assert_eq!(upgrade(&mut config), Ok(CURRENT_VERSION));
+        assert_eq!(config, expected);
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        assert!(upgrade(&mut fixture(r#"{ "version": 99 }"#)).is_err());
+        assert!(upgrade(&mut fixture(r#"{ "version": "1" }"#)).is_err());
+        assert!(upgrade(&mut fixture("[]")).is_err());
+    }
+
+    #[test]
+    fn finds_keys_missing_after_round_trip() {
+        let input = fixture(
+            r#"{ "version": 1, "hiden_browsers": ["Firefox"], "rules": [{ "match": { "hosts": ["a.com"], "patern": "x" } }], "profiles": {} }"#,
+        );
+        let parsed = fixture(r#"{ "version": 1, "hidden_browsers": [], "rules": [{ "match": { "hosts": ["a.com"] } }] }"#);
+        assert_eq!(unknown_keys(&input, &parsed), vec!["hiden_browsers", "rules[0].match.patern"]);
+        assert!(unknown_keys(&parsed, &parsed).is_empty());
+    }
+}
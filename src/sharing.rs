@@ -0,0 +1,450 @@
+use crate::schema;
+use serde_json::{Map, Value};
+
+// 可以单独导出、导入的配置部分
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Section {
+    Rules,
+    Actions,
+    Groups,
+    HiddenBrowsers,
+    Aliases,
+    DeepLinks,
+    SearchDirs,
+    Aria2,
+    Media,
+    Display,
+}
+
+pub const SECTIONS: &[Section] = &[
+    Section::Rules,
+    Section::Actions,
+    Section::Groups,
+    Section::HiddenBrowsers,
+    Section::Aliases,
+    Section::DeepLinks,
+    Section::SearchDirs,
+    Section::Aria2,
+    Section::Media,
+    Section::Display,
+];
+
+// 部分中条目的组织方式，决定条目如何识别
+enum Layout {
+    // 数组，按对象中的某个字段识别条目
+    ListBy(&'static str),
+    // 数组，值本身就是条目
+    Set,
+    // 对象，按键识别条目
+    Map,
+    // 每个字段是一个条目
+    Fields,
+}
+
+impl Section {
+    pub fn label(self) -> &'static str {
+        match self {
+            Section::Rules => "路由规则",
+            Section::Actions => "动作",
+            Section::Groups => "浏览器分组",
+            Section::HiddenBrowsers => "隐藏的浏览器",
+            Section::Aliases => "别名",
+            Section::DeepLinks => "应用链接改写",
+            Section::SearchDirs => "便携版搜索目录",
+            Section::Aria2 => "aria2",
+            Section::Media => "媒体播放器",
+            Section::Display => "显示设置",
+        }
+    }
+
+    fn fields(self) -> &'static [&'static str] {
+        match self {
+            Section::Rules => &["rules"],
+            Section::Actions => &["actions"],
+            Section::Groups => &["browser_groups"],
+            Section::HiddenBrowsers => &["hidden_browsers"],
+            Section::Aliases => &["aliases"],
+            Section::DeepLinks => &["deep_links"],
+            Section::SearchDirs => &["search_dirs"],
+            Section::Aria2 => &["aria2"],
+            Section::Media => &["media"],
+            Section::Display => &["icon_grid", "auto_hide_broken", "sort_mode", "browser_order"],
+        }
+    }
+
+    fn layout(self) -> Layout {
+        match self {
+            Section::Rules => Layout::ListBy("match"),
+            Section::Actions | Section::Groups => Layout::ListBy("name"),
+            Section::DeepLinks => Layout::ListBy("pattern"),
+            Section::HiddenBrowsers | Section::SearchDirs => Layout::Set,
+            Section::Aliases => Layout::Map,
+            Section::Aria2 | Section::Media | Section::Display => Layout::Fields,
+        }
+    }
+
+    // 团队预设可以提供的部分；显示设置无法区分用户是否改过，不参与叠加
+    fn layered(self) -> bool {
+        self != Section::Display
+    }
+
+    // 随配置方案切换的部分，保存在 profiles 中各方案自己的设置里
+    fn per_profile(self) -> bool {
+        matches!(self, Section::Rules | Section::HiddenBrowsers)
+    }
+}
+
+// 部分所在的对象：随方案切换的部分在指定方案的设置中（None 为顶层的默认方案），其他部分在顶层
+fn section_config<'a>(
+    config: &'a Map<String, Value>,
+    section: Section,
+    profile: Option<&str>,
+) -> Option<&'a Map<String, Value>> {
+    match profile {
+        Some(name) if section.per_profile() => config.get("profiles")?.get(name)?.as_object(),
+        _ => Some(config),
+    }
+}
+
+fn section_config_mut<'a>(
+    config: &'a mut Map<String, Value>,
+    section: Section,
+    profile: Option<&str>,
+) -> Option<&'a mut Map<String, Value>> {
+    match profile {
+        Some(name) if section.per_profile() => config.get_mut("profiles")?.get_mut(name)?.as_object_mut(),
+        _ => Some(config),
+    }
+}
+
+// 部分出现在哪些方案中：随方案切换的部分包括默认方案和所有命名方案
+fn section_profiles(config: &Map<String, Value>, section: Section) -> Vec<Option<String>> {
+    let mut profiles = vec![None];
+    if section.per_profile() {
+        let names = config.get("profiles").and_then(Value::as_object).into_iter().flat_map(|p| p.keys());
+        profiles.extend(names.cloned().map(Some));
+    }
+    profiles
+}
+
+fn value_key(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+// 部分中的条目：(标识, 内容)
+fn items(config: &Map<String, Value>, section: Section) -> Vec<(String, Value)> {
+    let field = section.fields()[0];
+    match section.layout() {
+        Layout::ListBy(id) => config
+            .get(field)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .map(|item| (item.get(id).map(value_key).unwrap_or_default(), item.clone()))
+            .collect(),
+        Layout::Set => config
+            .get(field)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .map(|item| (value_key(item), item.clone()))
+            .collect(),
+        Layout::Map => config
+            .get(field)
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect(),
+        Layout::Fields => section
+            .fields()
+            .iter()
+            .filter_map(|f| config.get(*f).map(|value| (f.to_string(), value.clone())))
+            .collect(),
+    }
+}
+
+// 替换、添加（value 为 Some）或删除（None）一个条目
+fn set_item(config: &mut Map<String, Value>, section: Section, key: &str, value: Option<Value>) {
+    let field = section.fields()[0];
+    match section.layout() {
+        Layout::ListBy(_) | Layout::Set => {
+            let layout = section.layout();
+            let same_key = |item: &Value| match layout {
+                Layout::ListBy(id) => item.get(id).map(value_key).unwrap_or_default() == key,
+                _ => value_key(item) == key,
+            };
+            let list = config
+                .entry(field)
+                .or_insert_with(|| Value::Array(Vec::new()));
+            let Some(list) = list.as_array_mut() else {
+                return;
+            };
+            let position = list.iter().position(same_key);
+            match (position, value) {
+                (Some(index), Some(value)) => list[index] = value,
+                (None, Some(value)) => list.push(value),
+                (Some(index), None) => {
+                    list.remove(index);
+                }
+                (None, None) => {}
+            }
+        }
+        Layout::Map => {
+            let map = config
+                .entry(field)
+                .or_insert_with(|| Value::Object(Map::new()));
+            let Some(map) = map.as_object_mut() else {
+                return;
+            };
+            match value {
+                Some(value) => map.insert(key.to_string(), value),
+                None => map.remove(key),
+            };
+        }
+        Layout::Fields => {
+            match value {
+                Some(value) => config.insert(key.to_string(), value),
+                None => config.remove(key),
+            };
+        }
+    }
+}
+
+// 导出选中的部分，路由规则和隐藏列表取自 profile 指定的方案；文件中带有配置版本，导入时可以升级
+pub fn export(config: &Value, sections: &[Section], profile: Option<&str>) -> Value {
+    let mut exported = Map::new();
+    exported.insert("version".to_string(), Value::from(schema::CURRENT_VERSION));
+    if let Some(config) = config.as_object() {
+        for &section in sections {
+            let Some(source) = section_config(config, section, profile) else {
+                continue;
+            };
+            for field in section.fields() {
+                if let Some(value) = source.get(*field) {
+                    exported.insert(field.to_string(), value.clone());
+                }
+            }
+        }
+    }
+    Value::Object(exported)
+}
+
+// 导入文件中与当前配置不同的条目；mine 为 None 的是新条目，否则是冲突
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportItem {
+    pub section: Section,
+    pub key: String,
+    pub mine: Option<Value>,
+    pub theirs: Value,
+    // true 为使用导入的内容，false 为保留我的
+    pub take_theirs: bool,
+}
+
+// 比较导入的文件和当前配置（路由规则和隐藏列表与 profile 指定的方案比较）；新条目默认导入，冲突默认保留我的
+pub fn plan_import(ours: &Value, imported: &Value, sections: &[Section], profile: Option<&str>) -> Vec<ImportItem> {
+    let empty = Map::new();
+    let ours = ours.as_object().unwrap_or(&empty);
+    let imported = imported.as_object().unwrap_or(&empty);
+
+    let mut plan = Vec::new();
+    for &section in sections {
+        let mine = section_config(ours, section, profile)
+            .map(|config| items(config, section))
+            .unwrap_or_default();
+        for (key, theirs) in items(imported, section) {
+            let existing = mine.iter().find(|(k, _)| *k == key).map(|(_, v)| v);
+            if existing == Some(&theirs) {
+                continue;
+            }
+            plan.push(ImportItem {
+                section,
+                key,
+                mine: existing.cloned(),
+                take_theirs: existing.is_none(),
+                theirs,
+            });
+        }
+    }
+    plan
+}
+
+pub fn apply_import(ours: &mut Value, plan: &[ImportItem], profile: Option<&str>) {
+    let Some(config) = ours.as_object_mut() else {
+        return;
+    };
+    for item in plan.iter().filter(|item| item.take_theirs) {
+        if let Some(target) = section_config_mut(config, item.section, profile) {
+            set_item(target, item.section, &item.key, Some(item.theirs.clone()));
+        }
+    }
+}
+
+// 把团队预设叠加在用户配置下面：用户没有的条目使用预设中的；
+// 预设的路由规则和隐藏列表叠加到每个方案，切换方案后同样生效
+pub fn layer(user: &Value, preset: &Value) -> Value {
+    let mut layered = user.clone();
+    let (Some(config), Some(preset)) = (layered.as_object_mut(), preset.as_object()) else {
+        return layered;
+    };
+    for &section in SECTIONS.iter().filter(|s| s.layered()) {
+        for profile in section_profiles(config, section) {
+            let Some(target) = section_config_mut(config, section, profile.as_deref()) else {
+                continue;
+            };
+            let mine = items(target, section);
+            for (key, value) in items(preset, section) {
+                if !mine.iter().any(|(k, _)| *k == key) {
+                    set_item(target, section, &key, Some(value));
+                }
+            }
+        }
+    }
+    layered
+}
+
+// 去掉与团队预设相同的条目，得到用户自己的配置；改过的预设条目作为用户的覆盖保留
+pub fn strip_preset(config: &Value, preset: &Value) -> Value {
+    let mut stripped = config.clone();
+    let (Some(config), Some(preset)) = (stripped.as_object_mut(), preset.as_object()) else {
+        return stripped;
+    };
+    for &section in SECTIONS.iter().filter(|s| s.layered()) {
+        for profile in section_profiles(config, section) {
+            let Some(target) = section_config_mut(config, section, profile.as_deref()) else {
+                continue;
+            };
+            let mine = items(target, section);
+            for (key, value) in items(preset, section) {
+                if mine.iter().any(|(k, v)| *k == key && *v == value) {
+                    set_item(target, section, &key, None);
+                }
+            }
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn mine() -> Value {
+        json!({
+            "version": 1,
+            "hidden_browsers": ["ie"],
+            "rules": [
+                { "match": { "hosts": ["github.com"] }, "target": { "browser": "firefox" } }
+            ],
+            "actions": [{ "name": "下载", "command": "wget" }],
+            "aliases": { "chrome": "Chrome (工作)" },
+            "icon_grid": true,
+            "usage": { "chrome": { "count": 3, "last_used": 10 } }
+        })
+    }
+
+    #[test]
+    fn exports_selected_sections_only() {
+        let exported = export(&mine(), &[Section::Rules, Section::Display], None);
+        assert_eq!(
+            exported,
+            json!({
+                "version": 1,
+                "rules": [
+                    { "match": { "hosts": ["github.com"] }, "target": { "browser": "firefox" } }
+                ],
+                "icon_grid": true
+            })
+        );
+    }
+
+    #[test]
+    fn imports_new_items_and_resolves_conflicts() {
+        let theirs = json!({
+            "hidden_browsers": ["ie", "edge"],
+            "rules": [
+                { "match": { "hosts": ["github.com"] }, "target": { "browser": "chrome" } },
+                { "match": { "hosts": ["jira.corp"] }, "target": { "browser": "edge" } }
+            ],
+            "actions": [{ "name": "下载", "command": "aria2c" }],
+            "aliases": { "chrome": "Chrome (工作)" }
+        });
+        let mut plan = plan_import(&mine(), &theirs, SECTIONS, None);
+        let summary: Vec<_> = plan.iter().map(|i| (i.section, i.mine.is_some(), i.take_theirs)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Section::Rules, true, false),
+                (Section::Rules, false, true),
+                (Section::Actions, true, false),
+                (Section::HiddenBrowsers, false, true),
+            ]
+        );
+
+        // 动作使用导入的，规则保留我的
+        plan[2].take_theirs = true;
+        let mut ours = mine();
+        apply_import(&mut ours, &plan, None);
+        assert_eq!(ours["hidden_browsers"], json!(["ie", "edge"]));
+        assert_eq!(ours["rules"][0]["target"], json!({ "browser": "firefox" }));
+        assert_eq!(ours["rules"][1]["target"], json!({ "browser": "edge" }));
+        assert_eq!(ours["actions"], json!([{ "name": "下载", "command": "aria2c" }]));
+        assert_eq!(ours["usage"], mine()["usage"]);
+    }
+
+    #[test]
+    fn team_preset_is_layered_under_user_config() {
+        let preset = json!({
+            "hidden_browsers": ["edge"],
+            "actions": [{ "name": "下载", "command": "aria2c" }, { "name": "归档", "command": "archive" }],
+            "aliases": { "firefox": "Firefox (测试)" },
+            "icon_grid": false
+        });
+        let layered = layer(&mine(), &preset);
+        assert_eq!(layered["hidden_browsers"], json!(["ie", "edge"]));
+        assert_eq!(
+            layered["actions"],
+            json!([{ "name": "下载", "command": "wget" }, { "name": "归档", "command": "archive" }])
+        );
+        assert_eq!(layered["aliases"]["firefox"], "Firefox (测试)");
+        assert_eq!(layered["icon_grid"], true);
+
+        // 保存时去掉预设提供的条目
+        assert_eq!(strip_preset(&layered, &preset), mine());
+    }
+
+    #[test]
+    fn rules_and_hidden_browsers_follow_the_profile() {
+        let mut ours = mine();
+        ours["profiles"] = json!({
+            "工作": { "hidden_browsers": ["opera"], "rules": [] }
+        });
+
+        let exported = export(&ours, &[Section::HiddenBrowsers, Section::Actions], Some("工作"));
+        assert_eq!(exported["hidden_browsers"], json!(["opera"]));
+        assert_eq!(exported["actions"], mine()["actions"]);
+
+        let theirs = json!({
+            "hidden_browsers": ["edge"],
+            "rules": [{ "match": { "hosts": ["github.com"] }, "target": { "browser": "firefox" } }]
+        });
+        let plan = plan_import(&ours, &theirs, SECTIONS, Some("工作"));
+        assert_eq!(plan.len(), 2);
+        let mut imported = ours.clone();
+        apply_import(&mut imported, &plan, Some("工作"));
+        assert_eq!(imported["profiles"]["工作"]["hidden_browsers"], json!(["opera", "edge"]));
+        assert_eq!(imported["profiles"]["工作"]["rules"], theirs["rules"]);
+        assert_eq!(imported["hidden_browsers"], json!(["ie"]));
+
+        // 团队预设叠加到每个方案，保存时从每个方案中去掉
+        let preset = json!({ "hidden_browsers": ["edge"] });
+        let layered = layer(&ours, &preset);
+        assert_eq!(layered["hidden_browsers"], json!(["ie", "edge"]));
+        assert_eq!(layered["profiles"]["工作"]["hidden_browsers"], json!(["opera", "edge"]));
+        assert_eq!(strip_preset(&layered, &preset), ours);
+    }
+}
@@ -0,0 +1,311 @@
+use serde_json::Value;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+// 等待其他窗口释放锁的时间；超过后放弃，不强行接管仍可能被持有的锁
+const LOCK_TIMEOUT: Duration = Duration::from_secs(3);
+// 锁只在写入时短暂持有，锁文件存在超过这个时间说明持有者已经退出
+const LOCK_STALE_AFTER: Duration = Duration::from_secs(10);
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+// 同目录下的附属文件，例如 config.json.lock、config.json.1
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+// 先写临时文件并同步到磁盘，再替换目标文件，崩溃时目标文件要么是旧内容要么是新内容
+pub fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // 临时文件名带进程号，避免多个进程同时写缓存时互相覆盖
+    let temp = sibling(path, &format!("{}.tmp", std::process::id()));
+    let result = File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(content)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp, path));
+    if result.is_err() {
+        fs::remove_file(&temp).ok();
+        return result;
+    }
+
+    // 目录项也同步到磁盘，否则断电后 rename 可能丢失
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        File::open(parent).and_then(|dir| dir.sync_all()).ok();
+    }
+    Ok(())
+}
+
+// 把当前文件复制为 .1，原来的 .1 改为 .2，依此类推，只保留 keep 个
+pub fn rotate_backups(path: &Path, keep: usize) -> io::Result<()> {
+    if keep == 0 {
+        return Ok(());
+    }
+    fs::remove_file(sibling(path, &keep.to_string())).ok();
+    for n in (1..keep).rev() {
+        let from = sibling(path, &n.to_string());
+        if from.exists() {
+            fs::rename(&from, sibling(path, &(n + 1).to_string()))?;
+        }
+    }
+    fs::copy(path, sibling(path, "1"))?;
+    Ok(())
+}
+
+// 从新到旧的备份文件
+pub fn backups(path: &Path, keep: usize) -> Vec<PathBuf> {
+    (1..=keep)
+        .map(|n| sibling(path, &n.to_string()))
+        .filter(|p| p.is_file())
+        .collect()
+}
+
+// 通过独占创建 .lock 文件实现的跨进程锁，释放时删除。
+// 锁文件内容为 "进程ID 序号"，用于判断持有者是否还在运行，以及释放时确认锁仍属于自己
+pub struct FileLock {
+    path: PathBuf,
+    owner: String,
+}
+
+impl FileLock {
+    pub fn acquire(path: &Path) -> io::Result<Self> {
+        static NEXT_OWNER: AtomicU64 = AtomicU64::new(0);
+
+        let lock_path = sibling(path, "lock");
+        if let Some(parent) = lock_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let owner = format!("{} {}", std::process::id(), NEXT_OWNER.fetch_add(1, Ordering::Relaxed));
+        let started = Instant::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&lock_path) {
+                Ok(mut file) => {
+                    write!(file, "{}", owner).ok();
+                    return Ok(Self { path: lock_path, owner });
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    let holder = fs::read_to_string(&lock_path).unwrap_or_default();
+                    if is_stale(&lock_path, &holder) {
+                        // 确认期间锁没有换人再删除
+                        if fs::read_to_string(&lock_path).is_ok_and(|current| current == holder) {
+                            fs::remove_file(&lock_path).ok();
+                        }
+                    } else if started.elapsed() > LOCK_TIMEOUT {
+                        return Err(io::Error::new(
+                            io::ErrorKind::TimedOut,
+                            format!("{} 被其他窗口占用", lock_path.display()),
+                        ));
+                    } else {
+                        thread::sleep(LOCK_RETRY_INTERVAL);
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+// 锁文件太旧，或记录的持有进程已经退出
+fn is_stale(lock_path: &Path, holder: &str) -> bool {
+    let too_old = fs::metadata(lock_path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| SystemTime::now().duration_since(t).ok())
+        .is_some_and(|age| age > LOCK_STALE_AFTER);
+    let holder_exited = holder
+        .split_whitespace()
+        .next()
+        .and_then(|pid| pid.parse().ok())
+        .and_then(process_alive)
+        .is_some_and(|alive| !alive);
+    too_old || holder_exited
+}
+
+// 进程是否还在运行；无法判断时返回 None
+#[cfg(unix)]
+fn process_alive(pid: u32) -> Option<bool> {
+    let proc = Path::new("/proc");
+    proc.is_dir().then(|| proc.join(pid.to_string()).exists())
+}
+
+#[cfg(windows)]
+fn process_alive(pid: u32) -> Option<bool> {
+    use windows_sys::Win32::Foundation::{CloseHandle, GetLastError, ERROR_INVALID_PARAMETER, STILL_ACTIVE};
+    use windows_sys::Win32::System::Threading::{GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION};
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle == 0 {
+            // 没有这个进程时返回 ERROR_INVALID_PARAMETER，其他错误（如权限不足）说明进程存在
+            return Some(GetLastError() != ERROR_INVALID_PARAMETER);
+        }
+        let mut exit_code = 0u32;
+        let ok = GetExitCodeProcess(handle, &mut exit_code) != 0;
+        CloseHandle(handle);
+        ok.then_some(exit_code == STILL_ACTIVE as u32)
+    }
+}
+
+impl Drop for FileLock {
+    // 锁被当作失效接管后，文件已属于别人，不能删除
+    fn drop(&mut self) {
+        if fs::read_to_string(&self.path).is_ok_and(|content| content == self.owner) {
+            fs::remove_file(&self.path).ok();
+        }
+    }
+}
+
+// 三方合并：base 是本窗口上次读取或写入的内容，ours 是本窗口的修改，theirs 是文件中的当前内容。
+// 对象按键递归合并，只有本窗口改过的部分覆盖文件中的内容；数组和其他值整体替换
+pub fn merge3(base: &Value, ours: &Value, theirs: &Value) -> Value {
+    if ours == base {
+        return theirs.clone();
+    }
+    let (Value::Object(base_map), Value::Object(our_map), Value::Object(their_map)) = (base, ours, theirs) else {
+        return ours.clone();
+    };
+
+    let mut merged = their_map.clone();
+    let keys = base_map.keys().chain(our_map.keys());
+    for key in keys {
+        match (base_map.get(key), our_map.get(key), their_map.get(key)) {
+            (Some(b), Some(o), Some(t)) => {
+                merged.insert(key.clone(), merge3(b, o, t));
+            }
+            // 本窗口新增或另一窗口删除后本窗口又改过
+            (b, Some(o), None) if b != Some(o) => {
+                merged.insert(key.clone(), o.clone());
+            }
+            // 两边都新增时以本窗口为准
+            (None, Some(o), Some(_)) => {
+                merged.insert(key.clone(), o.clone());
+            }
+            // 本窗口删除
+            (Some(b), None, Some(t)) if b == t => {
+                merged.remove(key);
+            }
+            _ => {}
+        }
+    }
+    Value::Object(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn writes_atomically_and_keeps_backups() {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().join("data/config.json");
+        for n in 1..=4 {
+            if path.exists() {
+                rotate_backups(&path, 2).unwrap();
+            }
+            write_atomic(&path, format!("{}", n).as_bytes()).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "4");
+        let kept: Vec<_> = backups(&path, 2).iter().map(|p| fs::read_to_string(p).unwrap()).collect();
+        assert_eq!(kept, vec!["3", "2"]);
+        // 没有残留的临时文件
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn lock_is_exclusive_until_dropped() {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().join("config.json");
+        let lock = FileLock::acquire(&path).unwrap();
+        assert!(sibling(&path, "lock").exists());
+
+        let waiter = {
+            let path = path.clone();
+            thread::spawn(move || {
+                let started = Instant::now();
+                let _lock = FileLock::acquire(&path).unwrap();
+                started.elapsed()
+            })
+        };
+        thread::sleep(Duration::from_millis(200));
+        drop(lock);
+        let waited = waiter.join().unwrap();
+        assert!(waited >= Duration::from_millis(150) && waited < LOCK_TIMEOUT);
+        assert!(!sibling(&path, "lock").exists());
+    }
+
+    #[test]
+    fn lock_held_by_a_running_process_is_not_taken_over() {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().join("config.json");
+        let lock_path = sibling(&path, "lock");
+        let holder = format!("{} 999", std::process::id());
+        fs::write(&lock_path, &holder).unwrap();
+
+        let err = FileLock::acquire(&path).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(fs::read_to_string(&lock_path).unwrap(), holder);
+    }
+
+    #[test]
+    fn lock_of_exited_process_is_taken_over() {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().join("config.json");
+        let lock_path = sibling(&path, "lock");
+        // 不可能存在的进程ID
+        fs::write(&lock_path, "4000000000 0").unwrap();
+
+        let started = Instant::now();
+        let lock = FileLock::acquire(&path).unwrap();
+        assert!(started.elapsed() < LOCK_TIMEOUT);
+
+        // 锁被别人接管后，释放时不删除对方的锁文件
+        fs::write(&lock_path, "4000000000 1").unwrap();
+        drop(lock);
+        assert_eq!(fs::read_to_string(&lock_path).unwrap(), "4000000000 1");
+    }
+
+    #[test]
+    fn merges_changes_from_both_windows() {
+        let base = json!({
+            "hidden_browsers": [],
+            "icon_grid": false,
+            "usage": { "chrome": { "count": 1, "last_used": 10 } },
+            "aliases": { "edge": "Edge" }
+        });
+        // 本窗口记录了 Chrome 的使用，删除了别名
+        let ours = json!({
+            "hidden_browsers": [],
+            "icon_grid": false,
+            "usage": { "chrome": { "count": 2, "last_used": 20 } }
+        });
+        // 另一个窗口隐藏了 IE、记录了 Firefox 的使用
+        let theirs = json!({
+            "hidden_browsers": ["ie"],
+            "icon_grid": false,
+            "usage": { "chrome": { "count": 1, "last_used": 10 }, "firefox": { "count": 1, "last_used": 15 } },
+            "aliases": { "edge": "Edge" }
+        });
+
+        assert_eq!(
+            merge3(&base, &ours, &theirs),
+            json!({
+                "hidden_browsers": ["ie"],
+                "icon_grid": false,
+                "usage": { "chrome": { "count": 2, "last_used": 20 }, "firefox": { "count": 1, "last_used": 15 } }
+            })
+        );
+        assert_eq!(merge3(&base, &base, &theirs), theirs);
+    }
+}